@@ -0,0 +1,29 @@
+#!/bin/sh
+# Rebuilds the release keys built into mlvm (keys/<lang>.asc) from the
+# fingerprints in src/commands/<lang>.rs. Every key comes from keys.openpgp.org,
+# or keyserver.ubuntu.com, and only the key with the listed fingerprint is kept.
+# Needs curl and gpg.
+set -eu
+cd "$(dirname "$0")"
+
+for lang in node bun; do
+    home=$(mktemp -d)
+    fingerprints=$(grep -o '"[0-9A-F]\{40\}"' "../src/commands/$lang.rs" | tr -d '"')
+    for fpr in $fingerprints; do
+        curl -fsS "https://keys.openpgp.org/vks/v1/by-fingerprint/$fpr" -o "$home/key.asc" ||
+            curl -fsS "https://keyserver.ubuntu.com/pks/lookup?op=get&options=mr&search=0x$fpr" -o "$home/key.asc"
+        gpg --homedir "$home" --batch --quiet --import "$home/key.asc"
+    done
+
+    {
+        echo "The $lang release keys built into mlvm. Generated by keys/update.sh; do not edit."
+        echo
+        for fpr in $fingerprints; do
+            # Fails unless this exact key was imported.
+            gpg --homedir "$home" --batch --list-keys "$fpr" >/dev/null
+            gpg --homedir "$home" --batch --armor --export "$fpr"
+        done
+    } > "$lang.asc.new"
+    mv "$lang.asc.new" "$lang.asc"
+    rm -rf "$home"
+done
@@ -1,72 +1,222 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "A Multi Language Version Manager", long_about = None)]
 pub struct Cli {
-#[command(subcommand)]
-pub language: Language,
+    #[command(subcommand)]
+    pub command: Commands,
+
+    /// Don't show progress bars (they are also hidden when stdout is not a terminal)
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// How long to wait for another mlvm process working on the same language (e.g. 30s, 5m)
+    #[arg(
+        long,
+        global = true,
+        env = "MLVM_LOCK_TIMEOUT",
+        default_value = "5m",
+        value_parser = humantime::parse_duration
+    )]
+    pub lock_timeout: std::time::Duration,
+
+    /// Where mlvm keeps toolchains, the cache and its settings (default: ~/.mlvm)
+    #[arg(long, global = true, env = "MLVM_HOME", value_name = "DIR")]
+    pub home: Option<std::path::PathBuf>,
+
+    /// Never use the network: version indexes and archives come from the cache only
+    #[arg(long, global = true, env = "MLVM_OFFLINE", value_parser = clap::builder::FalseyValueParser::new())]
+    pub offline: bool,
+
+    /// Output format: `text` for people, `json` for scripts
+    #[arg(long, global = true, value_enum, env = "MLVM_FORMAT", default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// Shorthand for `--format json`
+    #[arg(long, global = true)]
+    pub json: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
 }
 
 #[derive(Subcommand, Debug)]
-pub enum Language {
-/// Commands for managing Node.js versions
+pub enum Commands {
+    /// Commands for managing Node.js versions
     Node {
-    #[command(subcommand)]
-    command: NodeCommand,
+        #[command(subcommand)]
+        command: ToolchainCommand,
     },
-
-    Python { // <-- Add this new variant
+    /// Commands for managing Python versions (standalone builds)
+    Python {
         #[command(subcommand)]
-        command: PythonCommand,
+        command: ToolchainCommand,
     },
-
-    Go { 
+    /// Commands for managing Go versions
+    Go {
+        #[command(subcommand)]
+        command: ToolchainCommand,
+    },
+    /// Commands for managing Bun versions
+    Bun {
+        #[command(subcommand)]
+        command: ToolchainCommand,
+    },
+    /// Manage the cache of downloaded archives (~/.mlvm/cache)
+    Cache {
         #[command(subcommand)]
-        command: GoCommand },
+        command: CacheCommand,
+    },
+    /// Manage the OpenPGP keys used to verify upstream checksum files
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommand,
+    },
+    /// Show or change settings (~/.mlvm/config.toml)
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Rebuild the launchers in ~/.mlvm/shims (done automatically after installs)
+    Reshim,
+    /// Print shell code that puts the shims on PATH, e.g. `eval "$(mlvm env)"` in ~/.bashrc
+    Env {
+        /// The shell to print code for (default: the one in $SHELL)
+        #[arg(long, value_enum)]
+        shell: Option<Shell>,
+    },
+    /// Print `env` plus a hook that switches versions per directory, e.g. `eval "$(mlvm init bash)"`
+    Init {
+        /// The shell to print code for (default: the one in $SHELL)
+        shell: Option<Shell>,
+    },
+    /// Print the session variables for the current directory; this is what the hook runs
+    #[command(hide = true)]
+    HookEnv {
+        #[arg(long, value_enum)]
+        shell: Option<Shell>,
+    },
+    /// Run a tool of the selected version; this is what the shims call
+    #[command(hide = true, disable_help_flag = true)]
+    Exec {
+        language: Language,
+        /// The tool, then its arguments. Nothing after the tool name is an mlvm flag, and a
+        /// `--` right after it is dropped: shims run `mlvm exec <language> <tool> -- "$@"`
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true, value_name = "TOOL")]
+        command: Vec<std::ffi::OsString>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(alias = "powershell")]
+    Pwsh,
+    Nu,
+}
 
-    Bun { #[command(subcommand)] command: BunCommand },    
-// We can easily add Go { #[command(subcommand)] command: GoCommand } here later!
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Node,
+    Python,
+    Go,
+    Bun,
 }
 
+/// The commands every language supports.
 #[derive(Subcommand, Debug)]
-pub enum NodeCommand {
-    /// List all available remote versions of Node.js
+pub enum ToolchainCommand {
+    /// List all available remote versions
     ListRemote,
-    /// Install a specific version of Node.js
+    /// Install a version, resolved against the remote index
     Install {
-        /// The version to install (e.g., "18.17.1" or "v18.17.1")
-        version: String,
+        /// A version, partial version, range or alias (e.g., "18.17.1", "18", "^3.11", "lts/*", "latest").
+        /// Defaults to the project's version file (e.g. `.nvmrc`) in this directory or above
+        version: Option<String>,
+        /// Install without checking the archive against the upstream SHA-256 (not recommended)
+        #[arg(long)]
+        skip_verify: bool,
     },
+    /// Switch the `current` symlink to an installed version
     Use {
-        /// The version to use (e.g., "18.17.1")
-        version: String,
+        /// A version, partial version, range or alias, resolved against installed versions.
+        /// Defaults to the project's version file (e.g. `.nvmrc`) in this directory or above
+        version: Option<String>,
     },
+    /// List installed versions
     List,
+    /// Show the active version
+    Current,
+    /// Remove an installed version
+    Uninstall {
+        /// The version to remove (e.g., "18.17.1")
+        version: String,
+        /// Remove the version even if `current` points at it
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show aliases, or point an alias at a version spec (e.g., `alias default 18`)
+    Alias {
+        name: Option<String>,
+        /// The version spec the alias stands for
+        version: Option<String>,
+    },
+    /// Remove an alias
+    Unalias { name: String },
 }
 
 #[derive(Subcommand, Debug)]
-pub enum PythonCommand {
-    /// List all available remote versions of Python
-    ListRemote,
-    /// Install a specific version of Python
-    Install { // <-- Add this
-        /// The version to install (e.g., "3.11.5")
-        version: String,
+pub enum CacheCommand {
+    /// List cached archives
+    List,
+    /// Remove every cached archive
+    Clean,
+    /// Remove archives downloaded longer ago than the given age
+    Prune {
+        /// e.g. "30d", "2weeks", "12h"
+        #[arg(long, value_parser = humantime::parse_duration)]
+        older_than: std::time::Duration,
     },
-    Use { version: String }, // Add this
-    List
 }
 
 #[derive(Subcommand, Debug)]
-pub enum GoCommand {
-    ListRemote,
-    Install { version: String },
-    Use { version: String },
+pub enum KeysCommand {
+    /// List the trusted keys of every language
+    List,
+    /// Download the built-in release keys (all languages unless one is given)
+    Update { language: Option<SignedLanguage> },
+    /// Trust the OpenPGP public key(s) in a file or at a URL
+    Import {
+        language: SignedLanguage,
+        /// Path or http(s) URL of an ASCII-armored or binary public key
+        source: String,
+    },
+    /// Stop trusting a key
+    Remove {
+        language: SignedLanguage,
+        /// The key name shown by `mlvm keys list`
+        name: String,
+    },
 }
 
 #[derive(Subcommand, Debug)]
-pub enum BunCommand {
-    ListRemote,
-    Install { version: String },
-    Use { version: String },
-}
\ No newline at end of file
+pub enum ConfigCommand {
+    /// Show every setting
+    List,
+    /// Print one setting (e.g. `verify.policy`)
+    Get { key: String },
+    /// Change a setting (e.g. `set cache.max_size "2 GB"`)
+    Set { key: String, value: String },
+}
+
+/// Languages whose upstream signs its checksum files.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum SignedLanguage {
+    Node,
+    Bun,
+}
@@ -0,0 +1,28 @@
+// src/commands/alias.rs
+//
+// User-defined names for version specs (e.g. `default` -> `18`), stored per
+// language in `~/.mlvm/<lang>/.aliases.json`.
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ALIASES_FILE: &str = ".aliases.json";
+
+fn path(lang_dir: &Path) -> PathBuf {
+    lang_dir.join(ALIASES_FILE)
+}
+
+pub fn load(lang_dir: &Path) -> Result<BTreeMap<String, String>> {
+    match fs::read(path(lang_dir)) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save(lang_dir: &Path, aliases: &BTreeMap<String, String>) -> Result<()> {
+    fs::create_dir_all(lang_dir)?;
+    fs::write(path(lang_dir), serde_json::to_vec_pretty(aliases)?)?;
+    Ok(())
+}
@@ -1,96 +1,100 @@
 // src/commands/bun.rs
 use anyhow::{bail, Context, Result};
-use std::fs;
 use std::env;
-use std::io::Cursor;
-use zip::ZipArchive;
-#[cfg(windows)]
-use std::os::windows::fs as windows_fs;
-
-pub async fn list_remote() -> Result<()> {
-    println!("Fetching Bun versions...");
-    let client = reqwest::Client::builder().user_agent("mlvm-rust").build()?;
-    // Bun tags are simple
-    let url = "https://api.github.com/repos/oven-sh/bun/tags";
-    let response = client.get(url).send().await?.error_for_status()?;
-    let tags: Vec<serde_json::Value> = response.json().await?;
-
-    for tag in tags.iter().take(15) {
-        if let Some(name) = tag.get("name").and_then(|v| v.as_str()) {
-            println!("- {}", name);
-        }
+use std::path::{Path, PathBuf};
+
+use super::keys;
+use super::error::unsupported_platform;
+use super::index;
+use super::mirror::{self, Endpoint};
+use super::toolchain::{Artifact, ArchiveKind, RemoteVersion, Toolchain};
+
+pub struct Bun;
+
+impl Bun {
+    fn target() -> Result<&'static str> {
+        Ok(match (env::consts::OS, env::consts::ARCH) {
+            ("windows", "x86_64") => "bun-windows-x64",
+            ("linux", "x86_64") => "bun-linux-x64",
+            ("linux", "aarch64") => "bun-linux-aarch64",
+            ("macos", "x86_64") => "bun-darwin-x64",
+            ("macos", "aarch64") => "bun-darwin-aarch64",
+            _ => bail!(unsupported_platform(format!("{} {}", env::consts::OS, env::consts::ARCH))),
+        })
     }
-    Ok(())
 }
 
-pub async fn install(version: &str) -> Result<()> {
-    let version = if version.starts_with('v') { version.to_string() } else { format!("v{}", version) };
-    
-    println!("Installing Bun {}...", version);
-
-    // Determine URL based on OS
-    let target = match (env::consts::OS, env::consts::ARCH) {
-        ("windows", "x86_64") => "bun-windows-x64",
-        ("linux", "x86_64") => "bun-linux-x64",
-        ("linux", "aarch64") => "bun-linux-aarch64",
-        ("macos", "x86_64") => "bun-darwin-x64",
-        ("macos", "aarch64") => "bun-darwin-aarch64",
-        _ => bail!("Unsupported platform for Bun"),
-    };
-
-    let download_url = format!("https://github.com/oven-sh/bun/releases/download/{}/{}.zip", version, target);
-    println!("Downloading {}...", download_url);
-
-    let response = reqwest::get(&download_url).await?.error_for_status()?;
-    let bytes = response.bytes().await?;
-
-    let mlvm_dir = dirs::home_dir().context("No home")?.join(".mlvm");
-    let lang_dir = mlvm_dir.join("bun");
-    let install_path = lang_dir.join(&version);
-
-    if install_path.exists() {
-        println!("Bun {} already installed", version);
-        return Ok(());
+impl Toolchain for Bun {
+    const NAME: &'static str = "bun";
+    const DISPLAY_NAME: &'static str = "Bun";
+    const VERSION_FILES: &'static [&'static str] = &[".bun-version"];
+    const INDEX_URL: &'static str = "https://api.github.com/repos/oven-sh/bun";
+    const DOWNLOAD_URL: &'static str = "https://github.com/oven-sh/bun/releases/download";
+    const REMOTE_LIST_LIMIT: Option<usize> = Some(15);
+    // The key that signs `SHASUMS256.txt.asc` on every release page.
+    const RELEASE_KEYS: &'static [&'static str] = &["F3DCC08A8572C0749B3E18888EAB4D40A7B22B59"];
+    const RELEASE_KEYRING: &'static [u8] = include_bytes!("../../keys/bun.asc");
+
+    fn normalize_version(&self, version: &str) -> String {
+        let version = version.trim_start_matches("bun-");
+        if version.starts_with('v') {
+            version.to_string()
+        } else {
+            format!("v{}", version)
+        }
     }
 
-    println!("Unpacking...");
-    let temp = lang_dir.join("temp");
-    if temp.exists() { fs::remove_dir_all(&temp)?; }
-
-    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
-    archive.extract(&temp)?;
-
-    // Bun zips usually extract to a folder named like "bun-windows-x64"
-    let source = temp.join(target);
-    fs::create_dir_all(&lang_dir)?;
-    fs::rename(source, &install_path)?;
-    fs::remove_dir_all(temp)?;
-
-    println!("Installed Bun {}", version);
-    Ok(())
-}
-
-pub fn use_version(version: &str) -> Result<()> {
-    let version = if version.starts_with('v') { version.to_string() } else { format!("v{}", version) };
-    let lang_dir = dirs::home_dir().context("No home")?.join(".mlvm").join("bun");
-    let version_path = lang_dir.join(&version);
-
-    if !version_path.exists() {
-        bail!("Bun {} not installed", version);
+    async fn list_remote(&self) -> Result<Vec<RemoteVersion>> {
+        // Bun tags are simple
+        let tags: Vec<serde_json::Value> = index::json::<Self, _>("/tags").await?;
+
+        // Release tags are spelled `bun-v1.0.25`; we only keep the `v1.0.25` part.
+        Ok(tags
+            .iter()
+            .filter_map(|tag| tag.get("name").and_then(|v| v.as_str()))
+            .filter_map(|name| name.strip_prefix("bun-"))
+            .map(|name| RemoteVersion {
+                version: name.to_string(),
+                lts: None,
+            })
+            .collect())
     }
-    
-    let current = lang_dir.join("current");
-    if current.exists() || current.symlink_metadata().is_ok() {
-        if current.is_dir() { fs::remove_dir_all(&current).ok(); } else { fs::remove_file(&current).ok(); }
+
+    async fn artifact(&self, version: &str) -> Result<Artifact> {
+        let target = Self::target()?;
+        let filename = format!("{}.zip", target);
+        Ok(Artifact {
+            url: mirror::url::<Self>(Endpoint::Download, &format!("/bun-{}/{}", version, filename))?,
+            filename,
+            kind: ArchiveKind::Zip,
+            // Bun zips usually extract to a folder named like "bun-windows-x64"
+            root_dir: target.to_string(),
+        })
     }
 
-    #[cfg(unix)]
-    std::os::unix::fs::symlink(&version_path, &current)?;
-    #[cfg(windows)]
-    windows_fs::symlink_dir(&version_path, &current)?;
+    async fn sha256(&self, _version: &str, artifact: &Artifact) -> Result<String> {
+        keys::fetch_signed_sibling_list::<Self>(
+            &artifact.url,
+            "SHASUMS256.txt",
+            "SHASUMS256.txt.asc",
+            &artifact.filename,
+        )
+        .await
+    }
 
-    println!("Switched to Bun {}", version);
-    println!("Add this to PATH: {}", current.display());
+    /// Adds `bunx`, which Bun's own installer creates too: the binary runs `bun x`
+    /// when started under that name.
+    fn post_install(&self, root: &Path) -> Result<()> {
+        #[cfg(unix)]
+        let linked = std::os::unix::fs::symlink("bun", root.join("bunx"));
+        #[cfg(windows)]
+        let linked = std::fs::hard_link(root.join("bun.exe"), root.join("bunx.exe"))
+            .or_else(|_| std::fs::copy(root.join("bun.exe"), root.join("bunx.exe")).map(|_| ()));
+        linked.context("Failed to create bunx")
+    }
 
-    Ok(())
-}
\ No newline at end of file
+    fn bin_dirs(&self) -> Vec<PathBuf> {
+        // The `bun` binary sits at the top of the extracted folder.
+        vec![PathBuf::new()]
+    }
+}
@@ -0,0 +1,353 @@
+// src/commands/cache.rs
+//
+// Downloaded archives are kept in `~/.mlvm/cache/<url-hash>/<filename>` with a
+// `<filename>.json` record of where they came from and their SHA-256, so a
+// reinstall (or a fresh dev container sharing the cache) never re-downloads.
+// With `cache.max_size` set, the oldest archives are evicted to stay below it.
+//
+// An install holds the lock of its entry (see `commands::lock`) from looking it
+// up until it is committed or evicted. Cleaning up skips entries that are locked.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::cli::CacheCommand;
+use crate::commands::config;
+use crate::commands::engine;
+use crate::commands::error::{Error, ErrorKind};
+use crate::commands::lock::{self, Lock};
+use crate::commands::output::{self, status};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CacheRecord {
+    pub url: String,
+    pub sha256: String,
+    /// Seconds since the Unix epoch.
+    pub downloaded_at: u64,
+}
+
+/// `mlvm cache list`.
+#[derive(Serialize, Debug)]
+pub struct CacheListing {
+    pub dir: PathBuf,
+    /// Oldest first.
+    pub archives: Vec<CachedArchive>,
+    /// Bytes.
+    pub total_size: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CachedArchive {
+    pub file: String,
+    pub size: u64,
+    /// RFC 3339.
+    pub downloaded_at: String,
+    pub url: String,
+    pub sha256: String,
+}
+
+/// `mlvm cache clean` and `mlvm cache prune`.
+#[derive(Serialize, Debug)]
+pub struct CacheCleanup {
+    /// How many archives were removed.
+    pub removed: usize,
+}
+
+/// A cached archive and its record.
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub archive: PathBuf,
+    pub record: CacheRecord,
+}
+
+pub fn run(command: CacheCommand) -> Result<()> {
+    match command {
+        CacheCommand::List => list(),
+        CacheCommand::Clean => clean(),
+        CacheCommand::Prune { older_than } => prune(older_than),
+    }
+}
+
+/// `~/.mlvm/cache`
+pub fn cache_dir() -> Result<PathBuf> {
+    Ok(engine::mlvm_dir()?.join("cache"))
+}
+
+/// Where the archive for `url` lives once it has been downloaded and verified.
+pub fn archive_path(url: &str, filename: &str) -> Result<PathBuf> {
+    let key = format!("{:x}", Sha256::digest(url.as_bytes()));
+    Ok(cache_dir()?.join(&key[..16]).join(filename))
+}
+
+/// Where a download in progress is written before `commit` moves it into place.
+pub fn part_path(archive: &Path) -> PathBuf {
+    let mut name = archive.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    archive.with_file_name(name)
+}
+
+/// Locks the entry of `archive` for an install, waiting for another one to finish with it.
+pub fn lock(archive: &Path) -> Result<Lock> {
+    lock::cache_entry(&cache_dir()?, &entry_key(archive))
+}
+
+/// The lock of the entry of `archive`, unless an install is using it.
+fn try_lock(archive: &Path) -> Option<Lock> {
+    let dir = archive.parent()?;
+    lock::try_cache_entry(dir.parent()?, &entry_key(archive))
+}
+
+/// The name of the entry directory of `archive`.
+fn entry_key(archive: &Path) -> String {
+    archive.parent().and_then(Path::file_name).unwrap_or_default().to_string_lossy().to_string()
+}
+
+fn record_path(archive: &Path) -> PathBuf {
+    let mut name = archive.file_name().unwrap_or_default().to_os_string();
+    name.push(".json");
+    archive.with_file_name(name)
+}
+
+/// The cached archive for `url`, if there is a complete one.
+pub fn lookup(url: &str, filename: &str) -> Result<Option<CacheEntry>> {
+    let archive = archive_path(url, filename)?;
+    let Ok(bytes) = fs::read(record_path(&archive)) else {
+        return Ok(None);
+    };
+    let Ok(record) = serde_json::from_slice::<CacheRecord>(&bytes) else {
+        return Ok(None);
+    };
+    if record.url != url || !archive.is_file() {
+        return Ok(None);
+    }
+    Ok(Some(CacheEntry { archive, record }))
+}
+
+/// Promotes a verified `.part` download to a cache entry.
+pub fn commit(url: &str, archive: &Path, sha256: &str) -> Result<()> {
+    fs::rename(part_path(archive), archive)?;
+    let record = CacheRecord {
+        url: url.to_string(),
+        sha256: sha256.to_string(),
+        downloaded_at: now(),
+    };
+    fs::write(record_path(archive), serde_json::to_vec_pretty(&record)?)?;
+    enforce_max_size(archive)
+}
+
+/// Evicts the oldest entries (never `keep`) until the cache fits `cache.max_size`.
+fn enforce_max_size(keep: &Path) -> Result<()> {
+    let Some(max_size) = config::load()?.cache_max_size()? else {
+        return Ok(());
+    };
+    let entries = entries()?;
+    let size = |entry: &CacheEntry| fs::metadata(&entry.archive).map(|m| m.len()).unwrap_or(0);
+    let mut total: u64 = entries.iter().map(size).sum();
+    for entry in &entries {
+        if total <= max_size {
+            break;
+        }
+        if entry.archive != keep
+            && let Some(_lock) = try_lock(&entry.archive)
+        {
+            total -= size(entry);
+            evict(&entry.archive);
+        }
+    }
+    Ok(())
+}
+
+/// Drops an entry, e.g. when its bytes no longer match the published checksum. The caller
+/// holds the entry's lock.
+pub fn evict(archive: &Path) {
+    fs::remove_file(archive).ok();
+    fs::remove_file(record_path(archive)).ok();
+    fs::remove_file(part_path(archive)).ok();
+    if let Some(dir) = archive.parent() {
+        fs::remove_dir(dir).ok();
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Every complete entry in the cache, oldest first.
+pub fn entries() -> Result<Vec<CacheEntry>> {
+    let dir = cache_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for key_dir in fs::read_dir(dir)?.flatten() {
+        let Ok(files) = fs::read_dir(key_dir.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let path = file.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                let archive = path.with_extension("");
+                let record = fs::read(&path)
+                    .ok()
+                    .and_then(|b| serde_json::from_slice::<CacheRecord>(&b).ok());
+                if let Some(record) = record
+                    && archive.is_file()
+                {
+                    entries.push(CacheEntry { archive, record });
+                }
+            }
+        }
+    }
+    entries.sort_by_key(|e| e.record.downloaded_at);
+    Ok(entries)
+}
+
+pub fn list() -> Result<()> {
+    let archives: Vec<CachedArchive> = entries()?
+        .into_iter()
+        .map(|entry| CachedArchive {
+            file: entry.archive.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            size: fs::metadata(&entry.archive).map(|m| m.len()).unwrap_or(0),
+            downloaded_at: output::timestamp(UNIX_EPOCH + Duration::from_secs(entry.record.downloaded_at)),
+            url: entry.record.url,
+            sha256: entry.record.sha256,
+        })
+        .collect();
+    let listing = CacheListing {
+        dir: cache_dir()?,
+        total_size: archives.iter().map(|a| a.size).sum(),
+        archives,
+    };
+
+    output::emit(&listing, |listing| {
+        if listing.archives.is_empty() {
+            println!("The download cache is empty.");
+            return;
+        }
+        println!("Cached archives ({}):", listing.dir.display());
+        for archive in &listing.archives {
+            println!(
+                "- {}  {}  {}  {}",
+                archive.file,
+                engine::format_size(archive.size),
+                &archive.downloaded_at[..10],
+                archive.url
+            );
+        }
+        println!(
+            "{} archives, {} in total.",
+            listing.archives.len(),
+            engine::format_size(listing.total_size)
+        );
+    })
+}
+
+pub fn clean() -> Result<()> {
+    let entries = entries()?;
+    let in_use = clean_dir(&cache_dir()?)?;
+    let kept = |entry: &&CacheEntry| entry.archive.parent().is_some_and(|dir| in_use.iter().any(|d| d == dir));
+    let kept = entries.iter().filter(kept).count();
+    if kept > 0 {
+        status!("Kept {} cached archive(s) that another mlvm process is using.", kept);
+    }
+    let removed = entries.len() - kept;
+    output::emit(&CacheCleanup { removed }, |c| println!("Removed {} cached archive(s).", c.removed))
+}
+
+/// Removes everything from the cache in `dir` but the entries in use, and returns those.
+fn clean_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let Ok(key_dirs) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let mut in_use = Vec::new();
+    for key_dir in key_dirs.flatten() {
+        let path = key_dir.path();
+        // Lock files stay; deleting them would race with waiters.
+        if key_dir.file_name() == lock::LOCK_DIR || !path.is_dir() {
+            continue;
+        }
+        match lock::try_cache_entry(dir, &key_dir.file_name().to_string_lossy()) {
+            Some(_lock) => fs::remove_dir_all(&path)?,
+            None => in_use.push(path),
+        }
+    }
+    Ok(in_use)
+}
+
+/// Removes entries downloaded more than `older_than` ago, plus leftovers of
+/// interrupted downloads of the same age.
+pub fn prune(older_than: Duration) -> Result<()> {
+    if older_than.is_zero() {
+        bail!(Error::new(ErrorKind::InvalidInput, "--older-than must be greater than zero.")
+            .hint("Use `mlvm cache clean` to empty the cache."));
+    }
+    let cutoff = now().saturating_sub(older_than.as_secs());
+
+    let mut removed = 0;
+    for entry in entries()? {
+        if entry.record.downloaded_at < cutoff
+            && let Some(_lock) = try_lock(&entry.archive)
+        {
+            evict(&entry.archive);
+            removed += 1;
+        }
+    }
+
+    // Interrupted downloads never get a record; judge them by modification time.
+    let cutoff_time = UNIX_EPOCH + Duration::from_secs(cutoff);
+    if let Ok(key_dirs) = fs::read_dir(cache_dir()?) {
+        for key_dir in key_dirs.flatten() {
+            for file in fs::read_dir(key_dir.path()).into_iter().flatten().flatten() {
+                let path = file.path();
+                let stale = file.metadata().and_then(|m| m.modified()).is_ok_and(|t| t < cutoff_time);
+                if path.extension().is_some_and(|ext| ext == "part")
+                    && stale
+                    && let Some(_lock) = try_lock(&path)
+                {
+                    fs::remove_file(&path).ok();
+                }
+            }
+            fs::remove_dir(key_dir.path()).ok();
+        }
+    }
+
+    output::emit(&CacheCleanup { removed }, |c| println!("Removed {} cached archive(s).", c.removed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(dir: &Path, key: &str) -> PathBuf {
+        let archive = dir.join(key).join("node-v20.11.0-linux-x64.tar.xz");
+        fs::create_dir_all(archive.parent().unwrap()).unwrap();
+        fs::write(&archive, b"archive").unwrap();
+        fs::write(part_path(&archive), b"arch").unwrap();
+        archive
+    }
+
+    #[test]
+    fn cleaning_skips_entries_in_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let idle = entry(dir.path(), "0123456789abcdef");
+        let busy = entry(dir.path(), "fedcba9876543210");
+        let lock = lock::cache_entry(dir.path(), "fedcba9876543210").unwrap();
+        assert!(try_lock(&busy).is_none());
+        assert!(try_lock(&idle).is_some());
+
+        assert_eq!(clean_dir(dir.path()).unwrap(), [busy.parent().unwrap()]);
+        assert!(!idle.parent().unwrap().exists());
+        assert!(busy.is_file() && part_path(&busy).is_file());
+
+        drop(lock);
+        assert!(clean_dir(dir.path()).unwrap().is_empty());
+        assert!(!busy.parent().unwrap().exists());
+    }
+}
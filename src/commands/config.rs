@@ -0,0 +1,402 @@
+// src/commands/config.rs
+//
+// User settings in TOML. The file is `<mlvm home>/config.toml`, or
+// `$XDG_CONFIG_HOME/mlvm/config.toml` (`%APPDATA%\mlvm\config.toml` on
+// Windows) when only that one exists. Every setting is optional; a missing
+// file means all defaults.
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::cli::ConfigCommand;
+use crate::commands::engine;
+use crate::commands::error::{Error, ErrorKind};
+use crate::commands::mirror;
+use crate::commands::output;
+
+const FILE_NAME: &str = "config.toml";
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub cache: CacheConfig,
+    pub verify: VerifyConfig,
+    pub python: PythonConfig,
+    pub http: HttpConfig,
+    pub shell: ShellConfig,
+    /// Per language, keyed by its name (`node`, `python`, ...).
+    pub mirrors: BTreeMap<String, MirrorConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MirrorConfig {
+    /// Base URLs replacing the upstream's version index, tried in order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<Vec<String>>,
+    /// Base URLs replacing the upstream's download location, tried in order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Largest total size of the download cache, e.g. `2 GB`. Oldest archives go first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<String>,
+    /// How long a downloaded version index is used before asking the server again, e.g. `1h`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_ttl: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct VerifyConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<VerifyPolicy>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct PythonConfig {
+    /// Which python-build-standalone build to install.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flavor: Option<PythonFlavor>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// PEM file with CA certificates to trust on top of the system ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<PathBuf>,
+    /// e.g. `30s`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<String>,
+    /// How long a request may wait for the server, e.g. `1m`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShellConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_install: Option<AutoInstall>,
+}
+
+/// What the shell hook does when a project asks for a version that is not installed.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoInstall {
+    /// Say which command installs it (the default).
+    #[default]
+    Never,
+    /// Ask on the terminal, then install.
+    Ask,
+    /// Install without asking.
+    Always,
+}
+
+/// What to do when a download cannot be checked against a published checksum.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyPolicy {
+    /// Refuse to install (the default).
+    #[default]
+    Require,
+    /// Install anyway, with a warning.
+    Warn,
+    /// Never look for checksums, as if `--skip-verify` were always given.
+    Skip,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PythonFlavor {
+    #[default]
+    InstallOnly,
+    /// Debug symbols removed; noticeably smaller.
+    InstallOnlyStripped,
+}
+
+impl PythonFlavor {
+    /// The suffix of the asset name, e.g. `install_only` in `...-install_only.tar.gz`.
+    pub fn asset_suffix(self) -> &'static str {
+        match self {
+            PythonFlavor::InstallOnly => "install_only",
+            PythonFlavor::InstallOnlyStripped => "install_only_stripped",
+        }
+    }
+}
+
+/// Every key `mlvm config` understands, with a one-line description.
+const KEYS: &[(&str, &str)] = &[
+    ("cache.max_size", "Largest size of the download cache, e.g. `2 GB` (default: unlimited)"),
+    ("cache.index_ttl", "Reuse downloaded version indexes for this long, e.g. `10m` (default: 1h)"),
+    ("verify.policy", "require | warn | skip: what to do without a checksum (default: require)"),
+    ("python.flavor", "install_only | install_only_stripped (default: install_only)"),
+    ("http.ca_bundle", "PEM file of extra CA certificates, e.g. for a TLS-intercepting proxy"),
+    ("http.connect_timeout", "Give up connecting after this long (default: 30s)"),
+    ("http.timeout", "Give up on a server that sends nothing for this long (default: 60s)"),
+    ("shell.auto_install", "never | ask | always: install versions a project asks for on `cd` (default: never)"),
+];
+/// Languages that take `mirrors.<lang>.download` and `mirrors.<lang>.index`.
+const MIRROR_LANGUAGES: [&str; 4] = ["node", "python", "go", "bun"];
+
+/// `KEYS` plus the mirror keys of every language.
+fn all_keys() -> Vec<(String, &'static str)> {
+    let mut keys: Vec<(String, &str)> = KEYS.iter().map(|(k, d)| (k.to_string(), *d)).collect();
+    for lang in MIRROR_LANGUAGES {
+        keys.push((format!("mirrors.{}.download", lang), "Comma separated download base URLs"));
+        keys.push((format!("mirrors.{}.index", lang), "Comma separated version index base URLs"));
+    }
+    keys
+}
+
+/// One setting, as shown by `mlvm config get` / `set`.
+#[derive(Serialize, Debug)]
+pub struct Setting {
+    pub key: String,
+    /// `None` when not set, i.e. the default applies.
+    pub value: Option<String>,
+}
+
+/// `mlvm config list`.
+#[derive(Serialize, Debug)]
+pub struct Settings {
+    pub path: PathBuf,
+    pub settings: Vec<Setting>,
+}
+
+pub fn run(command: ConfigCommand) -> Result<()> {
+    match command {
+        ConfigCommand::List => list(),
+        ConfigCommand::Get { key } => {
+            let setting = Setting { value: load()?.get(&key)?, key };
+            output::emit(&setting, |s| match &s.value {
+                Some(value) => println!("{}", value),
+                None => println!("(not set)"),
+            })
+        }
+        ConfigCommand::Set { key, value } => {
+            let mut config = load()?;
+            config.set(&key, &value)?;
+            save(&config)?;
+            let setting = Setting { value: config.get(&key)?, key };
+            output::emit(&setting, |s| println!("{} = {}", s.key, value))
+        }
+    }
+}
+
+/// The config file in use: an existing one, or where a new one will be written.
+pub fn path() -> Result<PathBuf> {
+    let home = engine::mlvm_dir()?.join(FILE_NAME);
+    if home.exists() {
+        return Ok(home);
+    }
+    if let Some(xdg) = dirs::config_dir().map(|d| d.join("mlvm").join(FILE_NAME))
+        && xdg.exists()
+    {
+        return Ok(xdg);
+    }
+    Ok(home)
+}
+
+pub fn load() -> Result<Config> {
+    let path = path()?;
+    let Ok(text) = fs::read_to_string(&path) else {
+        return Ok(Config::default());
+    };
+    toml::from_str(&text).with_context(|| {
+        Error::new(ErrorKind::InvalidInput, format!("Invalid config file {}", path.display()))
+            .hint("Fix the file, or remove it to go back to the defaults.")
+    })
+}
+
+fn save(config: &Config) -> Result<()> {
+    let path = path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, toml::to_string_pretty(config)?)?;
+    Ok(())
+}
+
+fn list() -> Result<()> {
+    let config = load()?;
+    let keys = all_keys();
+    let settings = Settings {
+        path: path()?,
+        settings: keys
+            .iter()
+            .map(|(key, _)| Ok(Setting { key: key.clone(), value: config.get(key)? }))
+            .collect::<Result<_>>()?,
+    };
+    output::emit(&settings, |settings| {
+        println!("Settings ({}):", settings.path.display());
+        for (setting, (_, description)) in settings.settings.iter().zip(&keys) {
+            match &setting.value {
+                Some(value) => println!("  {} = {}", setting.key, value),
+                None => println!("  {} (not set)  # {}", setting.key, description),
+            }
+        }
+    })
+}
+
+impl Config {
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(match key {
+            "cache.max_size" => self.cache.max_size.clone(),
+            "cache.index_ttl" => self.cache.index_ttl.clone(),
+            "verify.policy" => self.verify.policy.map(|p| enum_name(&p)),
+            "python.flavor" => self.python.flavor.map(|f| enum_name(&f)),
+            "http.ca_bundle" => self.http.ca_bundle.as_ref().map(|p| p.display().to_string()),
+            "http.connect_timeout" => self.http.connect_timeout.clone(),
+            "http.timeout" => self.http.timeout.clone(),
+            "shell.auto_install" => self.shell.auto_install.map(|a| enum_name(&a)),
+            _ => {
+                let (lang, endpoint) = mirror_key(key)?;
+                let mirror = self.mirrors.get(lang);
+                match endpoint {
+                    "download" => mirror.and_then(|m| m.download.as_ref()),
+                    _ => mirror.and_then(|m| m.index.as_ref()),
+                }
+                .map(|urls| urls.join(","))
+            }
+        })
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "cache.max_size" => {
+                parse_size(value)?;
+                self.cache.max_size = Some(value.to_string());
+            }
+            "cache.index_ttl" => {
+                parse_ttl(value)?;
+                self.cache.index_ttl = Some(value.to_string());
+            }
+            "verify.policy" => self.verify.policy = Some(parse_enum(key, value)?),
+            "python.flavor" => self.python.flavor = Some(parse_enum(key, value)?),
+            "shell.auto_install" => self.shell.auto_install = Some(parse_enum(key, value)?),
+            "http.ca_bundle" => {
+                let path = PathBuf::from(value);
+                if !path.is_file() {
+                    bail!(invalid(format!("{} is not a file", path.display())));
+                }
+                self.http.ca_bundle = Some(fs::canonicalize(path)?);
+            }
+            "http.connect_timeout" => {
+                parse_duration(value)?;
+                self.http.connect_timeout = Some(value.to_string());
+            }
+            "http.timeout" => {
+                parse_duration(value)?;
+                self.http.timeout = Some(value.to_string());
+            }
+            _ => {
+                let (lang, endpoint) = mirror_key(key)?;
+                let urls = mirror::split_list(value);
+                if let Some(bad) = urls
+                    .iter()
+                    .find(|u| !u.starts_with("http://") && !u.starts_with("https://"))
+                {
+                    bail!(invalid(format!("'{}' is not an http(s) URL", bad)));
+                }
+                let mirror = self.mirrors.entry(lang.to_string()).or_default();
+                let urls = (!urls.is_empty()).then_some(urls);
+                match endpoint {
+                    "download" => mirror.download = urls,
+                    _ => mirror.index = urls,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `cache.max_size` in bytes.
+    pub fn cache_max_size(&self) -> Result<Option<u64>> {
+        self.cache.max_size.as_deref().map(parse_size).transpose()
+    }
+
+    /// `cache.index_ttl`; zero means always revalidate.
+    pub fn index_ttl(&self) -> Result<Option<Duration>> {
+        self.cache.index_ttl.as_deref().map(parse_ttl).transpose()
+    }
+}
+
+fn unknown_key(key: &str) -> Error {
+    let known: Vec<&str> = KEYS.iter().map(|(k, _)| *k).collect();
+    invalid(format!("Unknown setting '{}'.", key)).hint(format!(
+        "Known settings: {}, mirrors.<language>.download, mirrors.<language>.index",
+        known.join(", ")
+    ))
+}
+
+/// Splits `mirrors.<lang>.<download|index>`.
+fn mirror_key(key: &str) -> Result<(&str, &str)> {
+    match key.split('.').collect::<Vec<_>>().as_slice() {
+        ["mirrors", lang, endpoint @ ("download" | "index")] if MIRROR_LANGUAGES.contains(lang) => {
+            Ok((lang, endpoint))
+        }
+        _ => bail!(unknown_key(key)),
+    }
+}
+
+/// The serialized (TOML) spelling of an enum value.
+fn enum_name<T: Serialize>(value: &T) -> String {
+    toml::Value::try_from(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn parse_enum<T: for<'de> Deserialize<'de>>(key: &str, value: &str) -> Result<T> {
+    T::deserialize(toml::Value::String(value.to_string()))
+        .map_err(|_| invalid(format!("Invalid value '{}' for {}", value, key)).into())
+}
+
+/// `30s`, `2m`, `1m 30s`.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let duration = humantime::parse_duration(input.trim())
+        .with_context(|| invalid(format!("Invalid duration '{}', expected e.g. `30s`", input)))?;
+    if duration.is_zero() {
+        bail!(invalid(format!("Duration '{}' must be longer than zero", input)));
+    }
+    Ok(duration)
+}
+
+fn parse_ttl(input: &str) -> Result<Duration> {
+    humantime::parse_duration(input.trim())
+        .with_context(|| invalid(format!("Invalid duration '{}', expected e.g. `1h`", input)))
+}
+
+/// `500 MB`, `2GB`, `1.5 GiB`, `1048576`.
+pub fn parse_size(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number
+        .parse()
+        .with_context(|| invalid(format!("Invalid size '{}', expected e.g. `2 GB`", input)))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        other => bail!(invalid(format!("Unknown size unit '{}' in '{}'", other, input))),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidInput, message)
+}
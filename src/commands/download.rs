@@ -0,0 +1,477 @@
+// src/commands/download.rs
+//
+// Streams an HTTP response straight into the extractor. Memory use stays
+// bounded by a handful of network chunks no matter how big the archive is:
+//
+//   HTTP body -> SHA-256 -> (gzip | zstd) decoder -> tar unpack
+//                        \-> archive file (for the download cache)
+//
+// Interrupted downloads are resumed with HTTP `Range` requests.
+//
+// Zip archives need random access, so they are extracted from the archive
+// file once the download has finished.
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use indicatif::ProgressBar;
+use reqwest::header::RANGE;
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::commands::engine;
+use crate::commands::error::{Error, ErrorKind};
+use crate::commands::extract;
+use crate::commands::http;
+use crate::commands::output::status;
+use crate::commands::progress;
+use crate::commands::toolchain::ArchiveKind;
+
+/// How many network chunks may be buffered between the download and the extractor.
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Downloads the first of `urls` (the same file on several mirrors) that answers into
+/// `archive_path` and unpacks it into `dest` on the fly.
+/// Returns the SHA-256 (lowercase hex) of the downloaded bytes, so the caller can
+/// compare it against the published checksum before using anything in `dest`.
+///
+/// If `archive_path` already holds the start of the file (an earlier run was
+/// interrupted), only the rest is requested with an HTTP `Range` header. The same
+/// happens when the connection drops or stalls mid-download: from the next mirror if
+/// there is one, otherwise from the same URL after a backoff. A server that ignores `Range`
+/// gets the download started over. Saved bytes that do not unpack are deleted.
+pub async fn download_and_unpack(
+    urls: &[String],
+    kind: ArchiveKind,
+    dest: &Path,
+    archive_path: &Path,
+) -> Result<String> {
+    fs::create_dir_all(dest)?;
+    if let Some(parent) = archive_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut unpacker = Unpacker::start(kind, dest);
+    let mut hasher = Sha256::new();
+    let downloaded = download(urls, archive_path, &mut hasher, &mut unpacker).await;
+
+    let spinner = progress::spinner("Unpacking...");
+    let extracted = match unpacker.finish().await {
+        Some(extracted) => extracted,
+        // Only unpack a zip that arrived in full.
+        None if downloaded.is_ok() => unpack_zip_file(archive_path, dest).await,
+        None => Ok(()),
+    };
+    spinner.finish_and_clear();
+    // A network error usually surfaces in the extractor as a truncated archive;
+    // report the cause, not the symptom.
+    downloaded?;
+    if let Err(e) = extracted {
+        // The saved bytes are not a valid archive, so resuming from them can never work.
+        fs::remove_file(archive_path).ok();
+        return Err(e).context("Failed to unpack archive");
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Tarballs are unpacked while they download. Decompression and unpacking are
+/// blocking, so they run on their own thread and pull chunks from a channel.
+/// Zips are unpacked from the archive file afterwards, so they have no thread.
+struct Unpacker {
+    kind: ArchiveKind,
+    dest: PathBuf,
+    tx: Option<mpsc::Sender<Bytes>>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl Unpacker {
+    fn start(kind: ArchiveKind, dest: &Path) -> Self {
+        let mut unpacker = Unpacker { kind, dest: dest.to_path_buf(), tx: None, thread: None };
+        unpacker.spawn();
+        unpacker
+    }
+
+    fn spawn(&mut self) {
+        if let ArchiveKind::TarGz | ArchiveKind::TarZst = self.kind {
+            let (tx, rx) = mpsc::channel::<Bytes>(CHANNEL_CAPACITY);
+            let (kind, dest) = (self.kind, self.dest.clone());
+            self.tx = Some(tx);
+            self.thread = Some(tokio::task::spawn_blocking(move || {
+                unpack_tar(ChannelReader { rx, chunk: Bytes::new() }, kind, &dest)
+            }));
+        }
+    }
+
+    /// Hands a chunk to the extractor. The extractor may finish before the body does
+    /// (trailing padding after the tar end marker); from then on chunks are only hashed.
+    async fn feed(&mut self, chunk: Bytes) {
+        if let Some(sender) = &self.tx
+            && sender.send(chunk).await.is_err()
+        {
+            self.tx = None;
+        }
+    }
+
+    /// Throws away everything unpacked so far, for a download that starts over.
+    async fn restart(&mut self) -> Result<()> {
+        // Whatever the old thread made of the old bytes no longer matters.
+        let _ = self.finish().await;
+        fs::remove_dir_all(&self.dest)?;
+        fs::create_dir_all(&self.dest)?;
+        self.spawn();
+        Ok(())
+    }
+
+    /// Waits for the extractor to unpack what it was fed; `None` for zips.
+    async fn finish(&mut self) -> Option<Result<()>> {
+        // Closing the channel tells the extractor the archive has ended.
+        self.tx = None;
+        let thread = self.thread.take()?;
+        Some(thread.await.context("Extraction thread panicked").and_then(|r| r))
+    }
+}
+
+/// The network half of `download_and_unpack`: every byte of the file, in order, goes
+/// to the hasher, to the extractor (if any) and to `archive_path`.
+async fn download(
+    urls: &[String],
+    archive_path: &Path,
+    hasher: &mut Sha256,
+    unpacker: &mut Unpacker,
+) -> Result<()> {
+    // 1. Replay whatever an interrupted run already saved.
+    let mut offset = 0u64;
+    if let Ok(mut existing) = tokio::fs::File::open(archive_path).await {
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = existing.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            let chunk = Bytes::copy_from_slice(&buf[..n]);
+            hasher.update(&chunk);
+            unpacker.feed(chunk).await;
+            offset += n as u64;
+        }
+        if offset > 0 {
+            status!("Resuming download after {}...", engine::format_size(offset));
+        }
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(archive_path)
+        .await?;
+
+    // 2. Fetch the rest, picking up where we stopped whenever the connection drops.
+    let mut bar: Option<ProgressBar> = None;
+    let mut attempt = 1;
+    let mut mirror = 0;
+    loop {
+        let url = &urls[mirror];
+        let mut request = http::request(url)?;
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+        }
+        let mut response = match http::send(request).await {
+            Ok(response) => response,
+            Err(e) if mirror + 1 < urls.len() => {
+                status!("{} failed ({}), trying the next mirror...", url, e);
+                mirror += 1;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT => {}
+            // The saved file is already complete.
+            StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => break,
+            // The server ignored `Range` and sent everything again: start over with it,
+            // since the bytes replayed so far may not even be the same file.
+            status if status.is_success() && offset > 0 => {
+                status!("{} cannot resume downloads, starting over...", url);
+                file.set_len(0).await?;
+                *hasher = Sha256::new();
+                unpacker.restart().await?;
+                offset = 0;
+                if let Some(bar) = &bar {
+                    bar.set_position(0);
+                }
+            }
+            status if status.is_success() => {}
+            status if mirror + 1 < urls.len() => {
+                status!("{} returned {}, trying the next mirror...", url, status);
+                mirror += 1;
+                continue;
+            }
+            status if status == StatusCode::NOT_FOUND => bail!(Error::new(
+                ErrorKind::NotFound,
+                format!("{} returned {}.", url, status)
+            )
+            .hint("This version might not exist for your platform.")),
+            status => bail!("{} returned {}.", url, status),
+        }
+
+        let bar = bar.get_or_insert_with(|| {
+            let bar = progress::download_bar(response.content_length().map(|len| len + offset));
+            bar.set_position(offset);
+            bar
+        });
+
+        let streamed: Result<()> = async {
+            while let Some(chunk) = http::next_chunk(&mut response).await? {
+                bar.inc(chunk.len() as u64);
+                hasher.update(&chunk);
+                file.write_all(&chunk).await?;
+                offset += chunk.len() as u64;
+                unpacker.feed(chunk).await;
+            }
+            Ok(())
+        }
+        .await;
+
+        match streamed {
+            Ok(()) => break,
+            // Resume from the next mirror rather than waiting on one that just failed.
+            Err(e) if mirror + 1 < urls.len() && http::is_transient_error(&e) => {
+                bar.suspend(|| status!("{} stopped ({}), resuming from the next mirror...", url, e));
+                file.flush().await?;
+                mirror += 1;
+            }
+            Err(e)
+                if attempt < http::MAX_ATTEMPTS && http::is_transient_error(&e) =>
+            {
+                let wait = http::backoff(attempt);
+                bar.suspend(|| {
+                    status!("Connection lost ({}), resuming in {}s...", e, wait.as_secs())
+                });
+                file.flush().await?;
+                tokio::time::sleep(wait).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                file.flush().await.ok();
+                return Err(e).context("Download interrupted");
+            }
+        }
+    }
+
+    file.flush().await?;
+    if let Some(bar) = bar {
+        bar.finish_and_clear();
+    }
+    Ok(())
+}
+
+/// Unpacks an archive that is already on disk (a cache hit) into `dest`.
+/// Returns its SHA-256, which the caller checks just like a fresh download.
+pub async fn unpack_file(archive_path: &Path, kind: ArchiveKind, dest: &Path) -> Result<String> {
+    fs::create_dir_all(dest)?;
+    let spinner = progress::spinner("Unpacking...");
+
+    let path = archive_path.to_path_buf();
+    let sha256 = tokio::task::spawn_blocking(move || sha256_file(&path))
+        .await
+        .context("Hashing thread panicked")?;
+
+    let extracted = match kind {
+        ArchiveKind::Zip => unpack_zip_file(archive_path, dest).await,
+        ArchiveKind::TarGz | ArchiveKind::TarZst => {
+            let (path, dest) = (archive_path.to_path_buf(), dest.to_path_buf());
+            tokio::task::spawn_blocking(move || unpack_tar(fs::File::open(path)?, kind, &dest))
+                .await
+                .context("Extraction thread panicked")?
+        }
+    };
+    spinner.finish_and_clear();
+    extracted.context("Failed to unpack archive")?;
+
+    sha256
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn unpack_tar(reader: impl Read, kind: ArchiveKind, dest: &Path) -> Result<()> {
+    match kind {
+        ArchiveKind::TarZst => extract::tar(zstd::stream::read::Decoder::new(reader)?, dest),
+        _ => extract::tar(GzDecoder::new(reader), dest),
+    }
+}
+
+async fn unpack_zip_file(archive_path: &Path, dest: &Path) -> Result<()> {
+    let (path, dest) = (archive_path.to_path_buf(), dest.to_path_buf());
+    tokio::task::spawn_blocking(move || extract::zip(fs::File::open(&path)?, &dest))
+        .await
+        .context("Extraction thread panicked")?
+}
+
+/// Adapts the receiving end of the chunk channel to `std::io::Read`.
+struct ChannelReader {
+    rx: mpsc::Receiver<Bytes>,
+    chunk: Bytes,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.chunk.is_empty() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => self.chunk = chunk,
+                // Sender dropped: end of the archive.
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len());
+        buf[..n].copy_from_slice(&self.chunk.split_to(n));
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_support::{self, Response};
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use std::sync::Arc;
+
+    fn tar(content: &[u8]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "pkg/README", content).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    fn tar_gz(content: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&tar(content.as_bytes())).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zip(content: &str) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer.start_file("pkg/README", zip::write::FileOptions::default()).unwrap();
+        writer.write_all(content.as_bytes()).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// Serves `archive`, answering `Range` requests only if `ranges` is set.
+    fn serve_archive(archive: Vec<u8>, ranges: bool) -> Vec<String> {
+        let archive = Arc::new(archive);
+        let url = test_support::serve(move |request| {
+            let start = request
+                .headers
+                .get("range")
+                .filter(|_| ranges)
+                .and_then(|range| range.strip_prefix("bytes=")?.strip_suffix('-')?.parse::<usize>().ok());
+            match start {
+                Some(start) if start >= archive.len() => Response::new(416, ""),
+                Some(start) => Response::new(206, &archive[start..])
+                    .header("Content-Range", format!("bytes {}-{}/{}", start, archive.len() - 1, archive.len())),
+                None => Response::new(200, archive.to_vec()),
+            }
+        });
+        vec![format!("{}/pkg.tar.gz", url)]
+    }
+
+    fn sha256(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    #[tokio::test]
+    async fn unpacks_every_archive_kind() {
+        let zst = zstd::stream::encode_all(&tar(b"zstd")[..], 0).unwrap();
+        for (kind, name, archive, content) in [
+            (ArchiveKind::TarGz, "pkg.tar.gz", tar_gz("gzip"), "gzip"),
+            (ArchiveKind::TarZst, "pkg.tar.zst", zst, "zstd"),
+            (ArchiveKind::Zip, "pkg.zip", zip("zip"), "zip"),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let (dest, part) = (dir.path().join("staging"), dir.path().join(format!("{}.part", name)));
+            let urls = serve_archive(archive.clone(), true);
+            let sha = download_and_unpack(&urls, kind, &dest, &part).await.unwrap();
+            assert_eq!(sha, sha256(&archive), "{name}");
+            assert_eq!(fs::read(&part).unwrap(), archive, "{name}");
+            assert_eq!(fs::read_to_string(dest.join("pkg/README")).unwrap(), content, "{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn large_archives_stream_through_the_extractor() {
+        let dir = tempfile::tempdir().unwrap();
+        let (dest, part) = (dir.path().join("staging"), dir.path().join("pkg.tar.zst.part"));
+        // Far more chunks than the channel holds, so the download has to wait on the extractor.
+        let content: Vec<u8> = (0..8 << 20).map(|i: u32| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        let archive = zstd::stream::encode_all(&tar(&content)[..], 0).unwrap();
+        let urls = serve_archive(archive.clone(), true);
+
+        let sha = download_and_unpack(&urls, ArchiveKind::TarZst, &dest, &part).await.unwrap();
+        assert_eq!(sha, sha256(&archive));
+        assert!(fs::read(dest.join("pkg/README")).unwrap() == content);
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_server_ignores_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let (dest, part) = (dir.path().join("staging"), dir.path().join("pkg.tar.gz.part"));
+        let archive = tar_gz("fresh");
+        // What an earlier run saved of a different build of the file.
+        let stale = tar_gz("stale build of the same version");
+        fs::write(&part, &stale[..stale.len() / 2]).unwrap();
+
+        let urls = serve_archive(archive.clone(), false);
+        let sha = download_and_unpack(&urls, ArchiveKind::TarGz, &dest, &part).await.unwrap();
+        assert_eq!(sha, sha256(&archive));
+        assert_eq!(fs::read(&part).unwrap(), archive);
+        assert_eq!(fs::read_to_string(dest.join("pkg/README")).unwrap(), "fresh");
+    }
+
+    #[tokio::test]
+    async fn resumes_from_the_next_mirror_when_a_stream_breaks() {
+        let dir = tempfile::tempdir().unwrap();
+        let (dest, part) = (dir.path().join("staging"), dir.path().join("pkg.tar.gz.part"));
+        let archive = tar_gz("fresh");
+        let half = archive.len() / 2;
+        let body = archive.clone();
+        let broken = test_support::serve(move |_| Response::new(200, body.clone()).cut_off(half));
+        let mut urls = vec![format!("{}/pkg.tar.gz", broken)];
+        urls.extend(serve_archive(archive.clone(), true));
+
+        let sha = download_and_unpack(&urls, ArchiveKind::TarGz, &dest, &part).await.unwrap();
+        assert_eq!(sha, sha256(&archive));
+        assert_eq!(fs::read(&part).unwrap(), archive);
+        assert_eq!(fs::read_to_string(dest.join("pkg/README")).unwrap(), "fresh");
+    }
+
+    #[tokio::test]
+    async fn drops_a_partial_download_that_does_not_unpack() {
+        let dir = tempfile::tempdir().unwrap();
+        let (dest, part) = (dir.path().join("staging"), dir.path().join("pkg.tar.gz.part"));
+        let archive = tar_gz("fresh");
+        fs::write(&part, b"garbage, not gzip").unwrap();
+        let urls = serve_archive(archive.clone(), true);
+
+        let err = download_and_unpack(&urls, ArchiveKind::TarGz, &dest, &part).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("Failed to unpack archive"), "{err:#}");
+        assert!(!part.exists());
+
+        // So the next attempt downloads the whole file again.
+        fs::remove_dir_all(&dest).unwrap();
+        let sha = download_and_unpack(&urls, ArchiveKind::TarGz, &dest, &part).await.unwrap();
+        assert_eq!(sha, sha256(&archive));
+        assert_eq!(fs::read_to_string(dest.join("pkg/README")).unwrap(), "fresh");
+    }
+}
@@ -0,0 +1,1023 @@
+// src/commands/engine.rs
+//
+// The shared implementation of every per-language command. Languages only
+// describe themselves through the `Toolchain` trait; downloading, unpacking,
+// switching `current` and listing all happen here.
+//
+// The public functions return reports instead of printing them; they are the
+// library API (see `lib.rs`). `run` is the CLI on top: it prints the reports.
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::cli::ToolchainCommand;
+use crate::commands::alias;
+use crate::commands::cache;
+use crate::commands::config::{self, VerifyPolicy};
+use crate::commands::download;
+use crate::commands::error::{self, Error, ErrorKind};
+use crate::commands::http;
+use crate::commands::lock;
+use crate::commands::metadata::{self, InstallMetadata};
+use crate::commands::mirror;
+use crate::commands::output::{self, status};
+use crate::commands::resolve::{compare_versions, Candidate, VersionSpec};
+use crate::commands::shell;
+use crate::commands::shims;
+use crate::commands::staging::{self, Staging};
+use crate::commands::toolchain::{RemoteVersion, Toolchain};
+use crate::commands::verify;
+
+/// Name of the symlink pointing at the active version.
+pub const CURRENT_LINK: &str = "current";
+
+/// A version spec resolved against the remote index.
+#[derive(Serialize, Debug)]
+pub struct Resolved {
+    pub language: &'static str,
+    /// Normalized, i.e. the name of the install directory.
+    pub version: String,
+    pub lts: Option<String>,
+}
+
+/// A version file such as `.node-version` and the specs in it.
+#[derive(Serialize, Debug)]
+pub struct ProjectVersion {
+    pub file: PathBuf,
+    /// Highest priority first.
+    pub specs: Vec<String>,
+}
+
+/// `list-remote`: the versions published upstream, newest first.
+#[derive(Serialize, Debug)]
+pub struct RemoteList {
+    pub language: &'static str,
+    pub versions: Vec<RemoteVersion>,
+}
+
+/// The outcome of `install`.
+#[derive(Serialize, Debug)]
+pub struct InstallReport {
+    pub language: &'static str,
+    pub version: String,
+    pub path: PathBuf,
+    /// `false` when the version was installed already and nothing was done.
+    pub installed: bool,
+    pub source_url: Option<String>,
+    pub from_cache: bool,
+    /// SHA-256 of the archive that was unpacked.
+    pub sha256: Option<String>,
+    /// Whether `sha256` matched the checksum published upstream.
+    pub verified: bool,
+}
+
+/// `use` and `current`: the active version, if any.
+#[derive(Serialize, Debug)]
+pub struct ActiveVersion {
+    pub language: &'static str,
+    pub version: Option<String>,
+    /// The install directory of `version`.
+    pub path: Option<PathBuf>,
+    /// Directories to put on PATH, inside the `current` link.
+    pub bin_dirs: Vec<PathBuf>,
+}
+
+/// `list`: the installed versions, oldest first.
+#[derive(Serialize, Debug)]
+pub struct LocalList {
+    pub language: &'static str,
+    pub current: Option<String>,
+    pub versions: Vec<LocalVersion>,
+    /// Version directories of installs that never finished; they are not usable.
+    pub incomplete: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LocalVersion {
+    pub version: String,
+    pub current: bool,
+    pub lts: Option<String>,
+    /// Bytes on disk.
+    pub size: u64,
+    /// RFC 3339.
+    pub installed_at: Option<String>,
+    pub source_url: Option<String>,
+}
+
+/// The outcome of `uninstall`.
+#[derive(Serialize, Debug)]
+pub struct UninstallReport {
+    pub language: &'static str,
+    pub version: String,
+    /// `current` pointed at this version and was removed with it.
+    pub was_current: bool,
+}
+
+/// `alias` without arguments.
+#[derive(Serialize, Debug)]
+pub struct AliasList {
+    pub language: &'static str,
+    pub aliases: BTreeMap<String, String>,
+}
+
+/// One alias, as shown, set or removed.
+#[derive(Serialize, Debug)]
+pub struct Alias {
+    pub language: &'static str,
+    pub name: String,
+    pub target: String,
+}
+
+/// Dispatches a parsed subcommand to the engine for the given language and prints the result.
+pub async fn run<T: Toolchain>(toolchain: &T, command: ToolchainCommand) -> Result<()> {
+    match command {
+        ToolchainCommand::ListRemote => output::emit(&list_remote(toolchain).await?, print_remote_list::<T>),
+        ToolchainCommand::Install { version, skip_verify } => {
+            let specs = versions_or_project(toolchain, version)?;
+            let mut reports = Vec::new();
+            for spec in &specs {
+                reports.push(install(toolchain, spec, skip_verify).await?);
+            }
+            let print = |r: &InstallReport| {
+                if r.installed {
+                    println!("Successfully installed {} {}", T::DISPLAY_NAME, r.version);
+                } else {
+                    println!("{} {} is already installed.", T::DISPLAY_NAME, r.version);
+                }
+            };
+            // A version file can list several versions (pyenv), so this is always an array.
+            output::emit(&reports, |reports| reports.iter().for_each(print))
+        }
+        ToolchainCommand::Use { version } => {
+            // `current` takes the first of several versions; the shims find the others
+            // in the version file.
+            let version = versions_or_project(toolchain, version)?.remove(0);
+            let active = use_version(toolchain, &version)?;
+            let shims_on_path = shims::on_path()?;
+            output::emit(&active, |a| {
+                println!("Successfully switched to {} {}", T::DISPLAY_NAME, a.version.as_deref().unwrap_or_default());
+                if !shims_on_path {
+                    println!("\nTo put mlvm on your PATH, add this to your shell's startup file and restart it:");
+                    println!("  {}", shell::setup_line(shell::detect()));
+                }
+            })
+        }
+        ToolchainCommand::List => output::emit(&list_local(toolchain)?, print_local_list::<T>),
+        ToolchainCommand::Current => output::emit(&current(toolchain)?, |a| match &a.version {
+            Some(version) => println!("{}", version),
+            None => println!("No {} version is active. Pick one with `mlvm {} use <version>`.", T::DISPLAY_NAME, T::NAME),
+        }),
+        ToolchainCommand::Uninstall { version, force } => {
+            output::emit(&uninstall(toolchain, &version, force)?, |r| {
+                println!("Successfully uninstalled {} {}", T::DISPLAY_NAME, r.version);
+                if r.was_current {
+                    println!("No {} version is active now.", T::DISPLAY_NAME);
+                }
+            })
+        }
+        ToolchainCommand::Alias { name: None, .. } => output::emit(&list_aliases(toolchain)?, |list| {
+            if list.aliases.is_empty() {
+                println!("No {} aliases defined.", T::DISPLAY_NAME);
+            }
+            for (name, target) in &list.aliases {
+                println!("{} -> {}", name, target);
+            }
+        }),
+        ToolchainCommand::Alias { name: Some(name), version: None } => {
+            output::emit(&show_alias(toolchain, &name)?, |a| println!("{} -> {}", a.name, a.target))
+        }
+        ToolchainCommand::Alias { name: Some(name), version: Some(version) } => {
+            output::emit(&set_alias(toolchain, &name, &version)?, |a| println!("{} -> {}", a.name, a.target))
+        }
+        ToolchainCommand::Unalias { name } => {
+            output::emit(&remove_alias(toolchain, &name)?, |a| println!("Removed alias {}", a.name))
+        }
+    }
+}
+
+static HOME: OnceLock<PathBuf> = OnceLock::new();
+
+/// Called once from `main` with the value of `--home` / `MLVM_HOME`, before anything
+/// has used the home. Fails if the home was already resolved to another directory,
+/// since what ran before would have used that one.
+pub fn set_home(home: Option<PathBuf>) -> Result<()> {
+    let Some(home) = home.and_then(absolute_home) else {
+        return Ok(());
+    };
+    let resolved = HOME.get_or_init(|| home.clone());
+    if *resolved != home {
+        bail!(Error::new(
+            ErrorKind::InvalidInput,
+            format!("The mlvm home is already {}.", resolved.display())
+        )
+        .hint("Call set_home before anything else uses the mlvm home."));
+    }
+    Ok(())
+}
+
+/// `~/.mlvm`, unless `--home` or `MLVM_HOME` points elsewhere. Library callers that
+/// never call `set_home` get `MLVM_HOME` too. The first answer sticks.
+pub fn mlvm_dir() -> Result<PathBuf> {
+    if let Some(home) = HOME.get() {
+        return Ok(home.clone());
+    }
+    let home = match env::var_os("MLVM_HOME").and_then(|home| absolute_home(home.into())) {
+        Some(home) => home,
+        None => dirs::home_dir().context("Could not find home directory")?.join(".mlvm"),
+    };
+    Ok(HOME.get_or_init(|| home).clone())
+}
+
+/// A home given as a relative path, made absolute once: the shims and `mlvm env` write
+/// it down, and it must name the same directory from wherever they run.
+fn absolute_home(home: PathBuf) -> Option<PathBuf> {
+    if home.as_os_str().is_empty() {
+        return None;
+    }
+    std::path::absolute(home).ok()
+}
+
+/// `~/.mlvm/<lang>`
+pub fn lang_dir<T: Toolchain>() -> Result<PathBuf> {
+    Ok(mlvm_dir()?.join(T::NAME))
+}
+
+/// `lang_dir`, after cleaning up what interrupted runs left behind in it. Only commands
+/// that change the language directory call this.
+fn prepared_lang_dir<T: Toolchain>() -> Result<PathBuf> {
+    let lang_dir = lang_dir::<T>()?;
+    staging::recover(&lang_dir)?;
+    Ok(lang_dir)
+}
+
+/// The versions published upstream, newest first.
+pub async fn list_remote<T: Toolchain>(toolchain: &T) -> Result<RemoteList> {
+    status!("Fetching available {} versions...", T::DISPLAY_NAME);
+    Ok(RemoteList { language: T::NAME, versions: toolchain.list_remote().await? })
+}
+
+fn print_remote_list<T: Toolchain>(list: &RemoteList) {
+    if list.versions.is_empty() {
+        println!("No versions found. Please check if your platform is supported.");
+        return;
+    }
+    match T::REMOTE_LIST_LIMIT {
+        Some(limit) => println!("Available versions (Top {} shown):", limit),
+        None => println!("Available versions:"),
+    }
+    let limit = T::REMOTE_LIST_LIMIT.unwrap_or(usize::MAX);
+    for v in list.versions.iter().take(limit) {
+        match &v.lts {
+            Some(codename) => println!("- {} (LTS: {})", v.version, codename),
+            None => println!("- {}", v.version),
+        }
+    }
+}
+
+/// Picks the newest published version matching `version` (a version, range, alias or `lts/...`).
+pub async fn resolve<T: Toolchain>(toolchain: &T, version: &str) -> Result<Resolved> {
+    let spec = parse_spec::<T>(version)?;
+    let remote: Vec<Candidate> = toolchain
+        .list_remote()
+        .await?
+        .into_iter()
+        .map(|v| Candidate { version: v.version, lts: v.lts })
+        .collect();
+    let (resolved, lts) = match spec.pick(&remote, |v| toolchain.normalize_version(v)) {
+        Some(c) => (toolchain.normalize_version(&c.version), c.lts.clone()),
+        // Some indexes are incomplete (Bun's tag listing is paginated), so an exact
+        // version that isn't listed still gets a download attempt.
+        None => match spec.exact() {
+            Some(v) => (toolchain.normalize_version(&v), None),
+            None => bail!(Error::new(
+                ErrorKind::NotFound,
+                format!("No {} version matching '{}' was found.", T::DISPLAY_NAME, version)
+            )
+            .hint(format!("Run `mlvm {} list-remote` to see the available versions.", T::NAME))),
+        },
+    };
+    // The version becomes a directory name; an index or mirror must not pick the path.
+    check_version_name::<T>(&resolved)?;
+    Ok(Resolved { language: T::NAME, version: resolved, lts })
+}
+
+/// Installs the newest version matching `version`, verifying the download against the
+/// published checksum unless `skip_verify` is set.
+pub async fn install<T: Toolchain>(toolchain: &T, version: &str, skip_verify: bool) -> Result<InstallReport> {
+    // 1. Turn the spec into a concrete version using the remote index.
+    let Resolved { version, lts, .. } = resolve(toolchain, version).await?;
+    status!("Installing {} {}...", T::DISPLAY_NAME, version);
+
+    // 2. Bail out early if there is nothing to do.
+    let lang_dir = prepared_lang_dir::<T>()?;
+    let install_path = lang_dir.join(&version);
+    // Parallel installs of the same version wait here, then find it installed.
+    let _version_lock = lock::version(&lang_dir, &version)?;
+    if staging::is_complete(&install_path) {
+        return Ok(InstallReport {
+            language: T::NAME,
+            source_url: metadata::read(&lang_dir, &version).map(|m| m.source_url),
+            version,
+            path: install_path,
+            installed: false,
+            from_cache: false,
+            sha256: None,
+            verified: false,
+        });
+    }
+    if install_path.exists() {
+        status!("Removing an incomplete install of {} {}...", T::DISPLAY_NAME, version);
+        fs::remove_dir_all(&install_path)
+            .with_context(|| format!("Failed to remove {}", install_path.display()))?;
+    }
+
+    // 3. Ask the toolchain which file fits this host.
+    let artifact = toolchain.artifact(&version).await?;
+
+    // 4. Look up the published checksum before trusting any bytes.
+    let policy = config::load()?.verify.policy.unwrap_or_default();
+    let offline = http::is_offline();
+    let expected_sha256 = if offline {
+        // Cached archives were checked when they were downloaded; their record says against what.
+        None
+    } else if skip_verify || policy == VerifyPolicy::Skip {
+        let reason = if skip_verify { "--skip-verify given" } else { "verify.policy is `skip`" };
+        status!("WARNING: {}, the download will not be checked.", reason);
+        None
+    } else {
+        match toolchain.sha256(&version, &artifact).await {
+            Ok(sha) => Some(sha),
+            // A signature that does not check out is never something to install past.
+            Err(e) if policy == VerifyPolicy::Warn && error::kind(&e) != ErrorKind::Signature => {
+                status!(
+                    "WARNING: could not find the published checksum for {} ({:#}). \
+                    Installing anyway because verify.policy is `warn`.",
+                    artifact.filename, e
+                );
+                None
+            }
+            Err(e) => return Err(checksum_lookup_failed(e, &artifact.filename)),
+        }
+    };
+
+    // 5. Unpack into a scratch directory next to the final location, either from the
+    //    download cache or streamed from the network, hashing the archive on the way.
+    //    The staging directory is removed on every way out of this function.
+    let staging = Staging::create(&lang_dir)?;
+
+    let archive = cache::archive_path(&artifact.url, &artifact.filename)?;
+    // Held until the entry is committed or evicted, so cleaning the cache leaves it alone.
+    let _entry_lock = cache::lock(&archive)?;
+    // A cached archive that doesn't match what upstream publishes today is stale.
+    let cached = cache::lookup(&artifact.url, &artifact.filename)?.filter(|entry| {
+        expected_sha256
+            .as_ref()
+            .is_none_or(|expected| expected.eq_ignore_ascii_case(&entry.record.sha256))
+    });
+    if offline && cached.is_none() {
+        bail!(Error::new(
+            ErrorKind::Offline,
+            format!(
+                "{} is not in the download cache, so {} {} cannot be installed with --offline.",
+                artifact.filename,
+                T::DISPLAY_NAME,
+                version
+            )
+        )
+        .hint("Install it once without --offline to download it."));
+    }
+    let from_cache = cached.is_some();
+    let (actual_sha256, reference_sha256) = match cached {
+        Some(entry) => {
+            status!("Using cached {}...", artifact.filename);
+            let actual = download::unpack_file(&entry.archive, artifact.kind, staging.path()).await;
+            // With --skip-verify the cache record still catches a corrupted file.
+            (actual, Some(expected_sha256.clone().unwrap_or(entry.record.sha256)))
+        }
+        None => {
+            status!("Downloading {} from {}...", artifact.filename, artifact.url);
+            let part = cache::part_path(&archive);
+            let urls = mirror::candidates::<T>(&artifact.url)?;
+            let actual = download::download_and_unpack(&urls, artifact.kind, staging.path(), &part)
+                .await
+                .with_context(|| format!("Failed to install {} {}", T::DISPLAY_NAME, version));
+            (actual, expected_sha256.clone())
+        }
+    };
+
+    // 6. Nothing from the archive is used (or cached) unless its checksum matched.
+    let actual_sha256 = match actual_sha256 {
+        Ok(actual) => actual,
+        // A cached archive that does not unpack would fail every later install too.
+        Err(e) if from_cache => {
+            cache::evict(&archive);
+            return Err(e);
+        }
+        // Keep a partial download: the next attempt resumes from it. One that did not
+        // unpack has been deleted already.
+        Err(e) => return Err(e),
+    };
+    if let Some(reference) = &reference_sha256
+        && let Err(e) = verify::check_sha256(&actual_sha256, reference, &artifact.filename)
+    {
+        cache::evict(&archive);
+        return Err(e);
+    }
+    if !from_cache {
+        cache::commit(&artifact.url, &archive, &actual_sha256)?;
+    }
+    if expected_sha256.is_some() {
+        status!("Checksum verified.");
+    } else if offline {
+        status!("Checksum matches the one recorded when the archive was cached.");
+    }
+
+    let source = staging.root(&artifact.root_dir)?;
+    toolchain.post_install(&source)?;
+
+    // 7. Move it into place in one step.
+    staging.promote(&source, &install_path)?;
+    drop(staging);
+    // The metadata and the shims are shared with `use` and `uninstall`.
+    let lock = lock::language(&lang_dir)?;
+    metadata::write(&lang_dir, &InstallMetadata::new(&version, &artifact.url, lts))?;
+    shims::refresh();
+    drop(lock);
+
+    Ok(InstallReport {
+        language: T::NAME,
+        version,
+        path: install_path,
+        installed: true,
+        source_url: Some(artifact.url),
+        from_cache,
+        sha256: Some(actual_sha256),
+        verified: expected_sha256.is_some(),
+    })
+}
+
+/// Only a checksum that upstream does not publish is a checksum error. A failed request
+/// or signature check keeps its own kind, since the archive itself is not to blame.
+fn checksum_lookup_failed(e: anyhow::Error, filename: &str) -> anyhow::Error {
+    let message = format!("Could not find the published checksum for {}.", filename);
+    match error::kind(&e) {
+        ErrorKind::Checksum => e.context(
+            Error::new(ErrorKind::Checksum, message)
+                .hint("Use --skip-verify (or `mlvm config set verify.policy warn`) to install anyway."),
+        ),
+        // A signature that does not check out says so itself.
+        ErrorKind::Signature => e,
+        _ => e.context(message),
+    }
+}
+
+/// Makes the newest installed version matching `version` the active one.
+pub fn use_version<T: Toolchain>(toolchain: &T, version: &str) -> Result<ActiveVersion> {
+    let lang_dir = prepared_lang_dir::<T>()?;
+    let _lock = lock::language(&lang_dir)?;
+    let version = resolve_installed(toolchain, version)?;
+    status!("Switching to {} version {}...", T::DISPLAY_NAME, version);
+
+    let version_path = lang_dir.join(&version);
+
+    let current = lang_dir.join(CURRENT_LINK);
+    switch_current(&version_path, &current)?;
+
+    Ok(ActiveVersion {
+        language: T::NAME,
+        version: Some(version),
+        path: Some(version_path),
+        bin_dirs: toolchain.bin_dirs().into_iter().map(|bin| current.join(bin)).collect(),
+    })
+}
+
+/// Picks the newest installed version matching `version` (a version, range, alias or `lts/...`).
+pub fn resolve_installed<T: Toolchain>(toolchain: &T, version: &str) -> Result<String> {
+    let lang_dir = lang_dir::<T>()?;
+    let spec = parse_spec::<T>(version)?;
+    let installed: Vec<Candidate> = installed_versions::<T>()?
+        .into_iter()
+        .map(|v| Candidate {
+            lts: metadata::read(&lang_dir, &v).and_then(|m| m.lts),
+            version: v,
+        })
+        .collect();
+
+    match spec.pick(&installed, |v| toolchain.normalize_version(v)) {
+        Some(picked) => Ok(picked.version.clone()),
+        None => bail!(Error::new(
+            ErrorKind::NotInstalled,
+            format!("No installed {} version matches '{}'.", T::DISPLAY_NAME, version)
+        )
+        .hint(format!("Run `mlvm {} install {}` first.", T::NAME, version))),
+    }
+}
+
+/// The versions the shims run, highest priority first: those in `MLVM_<LANG>_VERSION`
+/// (set for one shell session), else those of the project's version file, else the
+/// one `current` points at.
+pub fn selected_versions<T: Toolchain>(toolchain: &T) -> Result<Vec<String>> {
+    let var = version_var::<T>();
+    let dir = std::env::current_dir().ok();
+    match selection(toolchain, std::env::var(&var).ok(), dir.as_deref()) {
+        Selection::Session(value) => value
+            .split([':', ' ', ','])
+            .filter(|spec| !spec.is_empty())
+            .map(|spec| resolve_installed(toolchain, spec).with_context(|| format!("{} is set to '{}'", var, value)))
+            .collect(),
+        Selection::Project(project) => project
+            .specs
+            .iter()
+            .map(|spec| {
+                resolve_installed(toolchain, spec)
+                    .with_context(|| format!("{} asks for {} {}", project.file.display(), T::DISPLAY_NAME, spec))
+            })
+            .collect(),
+        Selection::Current => Ok(current_version::<T>()?.into_iter().collect()),
+    }
+}
+
+/// What `selected_versions` goes by.
+#[derive(Debug)]
+enum Selection {
+    /// The value of `MLVM_<LANG>_VERSION`.
+    Session(String),
+    Project(ProjectVersion),
+    Current,
+}
+
+/// The precedence of `selected_versions`, given `MLVM_<LANG>_VERSION` and the working directory.
+fn selection<T: Toolchain>(toolchain: &T, session: Option<String>, dir: Option<&Path>) -> Selection {
+    if let Some(value) = session
+        && !value.trim().is_empty()
+    {
+        return Selection::Session(value);
+    }
+    match dir.and_then(|dir| project_version(toolchain, dir)) {
+        Some(project) => Selection::Project(project),
+        None => Selection::Current,
+    }
+}
+
+/// `MLVM_NODE_VERSION`, `MLVM_PYTHON_VERSION`, ...
+pub fn version_var<T: Toolchain>() -> String {
+    format!("MLVM_{}_VERSION", T::NAME.to_ascii_uppercase())
+}
+
+/// The version file that applies to `dir`, i.e. the nearest one in it or above it.
+pub fn project_version<T: Toolchain>(toolchain: &T, dir: &Path) -> Option<ProjectVersion> {
+    dir.ancestors().find_map(|dir| {
+        T::VERSION_FILES.iter().find_map(|name| {
+            let file = dir.join(name);
+            let specs = toolchain.parse_version_file(&fs::read_to_string(&file).ok()?);
+            (!specs.is_empty()).then_some(ProjectVersion { file, specs })
+        })
+    })
+}
+
+/// The version given on the command line, or else the one the project in the
+/// working directory asks for.
+fn versions_or_project<T: Toolchain>(toolchain: &T, version: Option<String>) -> Result<Vec<String>> {
+    if let Some(version) = version {
+        return Ok(vec![version]);
+    }
+    let dir = std::env::current_dir()?;
+    let files = T::VERSION_FILES.join(" or ");
+    let Some(project) = project_version(toolchain, &dir) else {
+        bail!(Error::new(
+            ErrorKind::InvalidInput,
+            format!("No version given, and there is no {} in {} or above.", files, dir.display())
+        )
+        .hint(format!("Pass a version, or write one to {} at the root of the project.", files)));
+    };
+    status!("Found {} with version {}", project.file.display(), project.specs.join(", "));
+    Ok(project.specs)
+}
+
+/// The active version, if any.
+pub fn current<T: Toolchain>(toolchain: &T) -> Result<ActiveVersion> {
+    let lang_dir = lang_dir::<T>()?;
+    let version = current_version::<T>()?;
+    Ok(ActiveVersion {
+        language: T::NAME,
+        path: version.as_ref().map(|v| lang_dir.join(v)),
+        bin_dirs: match version {
+            Some(_) => toolchain.bin_dirs().into_iter().map(|bin| lang_dir.join(CURRENT_LINK).join(bin)).collect(),
+            None => Vec::new(),
+        },
+        version,
+    })
+}
+
+/// Removes an installed version. The active version is only removed with `force`.
+///
+/// The downloaded archive stays in the cache, so installing the version again needs no
+/// download; `mlvm cache prune` and `mlvm cache clean` remove it.
+pub fn uninstall<T: Toolchain>(toolchain: &T, version: &str, force: bool) -> Result<UninstallReport> {
+    let version = toolchain.normalize_version(version);
+    check_version_name::<T>(&version)?;
+    let not_installed = || {
+        Error::new(ErrorKind::NotInstalled, format!("{} {} is not installed.", T::DISPLAY_NAME, version))
+            .hint(format!("Run `mlvm {} list` to see the installed versions.", T::NAME))
+    };
+    if is_reserved_dir(&version) {
+        bail!(not_installed());
+    }
+
+    let lang_dir = prepared_lang_dir::<T>()?;
+    // Only checked under the locks, so a parallel install or uninstall cannot change the answer.
+    let _version_lock = lock::version(&lang_dir, &version)?;
+    let _lock = lock::language(&lang_dir)?;
+    let version_path = lang_dir.join(&version);
+    if !version_path.is_dir() {
+        bail!(not_installed());
+    }
+
+    // Never leave `current` dangling: refuse, or take the link down with the version.
+    let is_current = current_version::<T>()?.as_deref() == Some(version.as_str());
+    if is_current {
+        if !force {
+            bail!(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} {} is the active version.", T::DISPLAY_NAME, version)
+            )
+            .hint(format!("Switch with `mlvm {} use <version>` first, or pass --force.", T::NAME)));
+        }
+        remove_link(&lang_dir.join(CURRENT_LINK));
+    }
+
+    status!("Uninstalling {} {}...", T::DISPLAY_NAME, version);
+    fs::remove_dir_all(&version_path)
+        .with_context(|| format!("Failed to remove {}", version_path.display()))?;
+    metadata::remove(&lang_dir, &version)?;
+    shims::refresh();
+
+    Ok(UninstallReport { language: T::NAME, version, was_current: is_current })
+}
+
+/// The installed versions, oldest first.
+pub fn list_local<T: Toolchain>(_toolchain: &T) -> Result<LocalList> {
+    let lang_dir = lang_dir::<T>()?;
+    let (versions, incomplete): (Vec<_>, Vec<_>) =
+        version_dirs::<T>()?.into_iter().partition(|(_, complete)| *complete);
+    let incomplete: Vec<String> = incomplete.into_iter().map(|(name, _)| name).collect();
+    for name in &incomplete {
+        status!(
+            "Ignoring incomplete install {}. Run `mlvm {} install {}` to repair it.",
+            name,
+            T::NAME,
+            name
+        );
+    }
+    let current = current_version::<T>()?;
+
+    let versions = versions
+        .into_iter()
+        .map(|(version, _)| {
+            let path = lang_dir.join(&version);
+            let meta = metadata::read(&lang_dir, &version);
+            let installed_at = meta
+                .as_ref()
+                .map(|m| UNIX_EPOCH + Duration::from_secs(m.installed_at))
+                .or_else(|| fs::metadata(&path).and_then(|m| m.modified()).ok());
+            LocalVersion {
+                current: current.as_deref() == Some(version.as_str()),
+                lts: meta.as_ref().and_then(|m| m.lts.clone()),
+                size: dir_size(&path),
+                installed_at: installed_at.map(output::timestamp),
+                source_url: meta.map(|m| m.source_url),
+                version,
+            }
+        })
+        .collect();
+    Ok(LocalList { language: T::NAME, current, versions, incomplete })
+}
+
+fn print_local_list<T: Toolchain>(list: &LocalList) {
+    if list.versions.is_empty() {
+        println!("No {} versions installed yet.", T::DISPLAY_NAME);
+        return;
+    }
+
+    // Gather every column first so they can be aligned.
+    let rows: Vec<[String; 4]> = list
+        .versions
+        .iter()
+        .map(|v| {
+            [
+                v.version.clone(),
+                format_size(v.size),
+                v.installed_at.as_ref().map(|t| t[..10].to_string()).unwrap_or_else(|| "-".to_string()),
+                v.source_url.clone().unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+
+    let version_width = rows.iter().map(|r| r[0].len()).max().unwrap_or(0);
+    let size_width = rows.iter().map(|r| r[1].len()).max().unwrap_or(0);
+
+    println!("Installed {} versions:", T::DISPLAY_NAME);
+    for (v, [version, size, date, url]) in list.versions.iter().zip(rows) {
+        println!(
+            "{} {:<vw$}  {:>sw$}  {}  {}{}",
+            if v.current { "*" } else { " " },
+            version,
+            size,
+            date,
+            url,
+            if v.current { "  (current)" } else { "" },
+            vw = version_width,
+            sw = size_width,
+        );
+    }
+}
+
+/// Parses a version spec, expanding a user-defined alias first.
+fn parse_spec<T: Toolchain>(input: &str) -> Result<VersionSpec> {
+    let aliases = alias::load(&lang_dir::<T>()?)?;
+    Ok(match aliases.get(input) {
+        Some(target) => VersionSpec::parse(target),
+        None => VersionSpec::parse(input),
+    })
+}
+
+pub fn list_aliases<T: Toolchain>(_toolchain: &T) -> Result<AliasList> {
+    Ok(AliasList { language: T::NAME, aliases: alias::load(&lang_dir::<T>()?)? })
+}
+
+pub fn show_alias<T: Toolchain>(_toolchain: &T, name: &str) -> Result<Alias> {
+    let aliases = alias::load(&lang_dir::<T>()?)?;
+    let Some(target) = aliases.get(name) else {
+        bail!(no_alias::<T>(name));
+    };
+    Ok(Alias { language: T::NAME, name: name.to_string(), target: target.clone() })
+}
+
+/// Points `name` at `target`, which may be any version spec.
+pub fn set_alias<T: Toolchain>(_toolchain: &T, name: &str, target: &str) -> Result<Alias> {
+    // An alias that looks like a version or a built-in name could never be reached.
+    if !matches!(VersionSpec::parse(name), VersionSpec::Exact(_)) || name.contains(['/', '\\']) {
+        bail!(Error::new(ErrorKind::InvalidInput, format!("'{}' cannot be used as an alias name.", name))
+            .hint("Alias names must not look like a version, a range or `lts/...`."));
+    }
+
+    let lang_dir = lang_dir::<T>()?;
+    let _lock = lock::language(&lang_dir)?;
+    let mut aliases = alias::load(&lang_dir)?;
+    aliases.insert(name.to_string(), target.to_string());
+    alias::save(&lang_dir, &aliases)?;
+    Ok(Alias { language: T::NAME, name: name.to_string(), target: target.to_string() })
+}
+
+pub fn remove_alias<T: Toolchain>(_toolchain: &T, name: &str) -> Result<Alias> {
+    let lang_dir = lang_dir::<T>()?;
+    let _lock = lock::language(&lang_dir)?;
+    let mut aliases = alias::load(&lang_dir)?;
+    let Some(target) = aliases.remove(name) else {
+        bail!(no_alias::<T>(name));
+    };
+    alias::save(&lang_dir, &aliases)?;
+    Ok(Alias { language: T::NAME, name: name.to_string(), target })
+}
+
+fn no_alias<T: Toolchain>(name: &str) -> Error {
+    Error::new(ErrorKind::NotFound, format!("No {} alias named '{}'.", T::DISPLAY_NAME, name))
+        .hint(format!("Run `mlvm {} alias` to list the aliases.", T::NAME))
+}
+
+/// Names of the installed versions of a language, oldest first.
+pub fn installed_versions<T: Toolchain>() -> Result<Vec<String>> {
+    Ok(version_dirs::<T>()?
+        .into_iter()
+        .filter(|(_, complete)| *complete)
+        .map(|(name, _)| name)
+        .collect())
+}
+
+/// Every version directory of a language, oldest first, and whether its install completed.
+fn version_dirs<T: Toolchain>() -> Result<Vec<(String, bool)>> {
+    let lang_dir = lang_dir::<T>()?;
+    if !lang_dir.exists() {
+        return Ok(Vec::new());
+    }
+    // Readers leave the directory alone (the shims get here on every run), so installs made
+    // before the marker existed count as complete until a mutating command stamps them.
+    let stamped = staging::is_migrated(&lang_dir);
+
+    let mut versions = Vec::new();
+    for entry in fs::read_dir(&lang_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        // `current` is a symlink, so `file_type` does not report it as a directory.
+        if entry.file_type()?.is_dir() && !is_reserved_dir(&name) {
+            let complete = !stamped || staging::is_complete(&entry.path());
+            versions.push((name, complete));
+        }
+    }
+    versions.sort_by(|a, b| compare_versions(&a.0, &b.0));
+    Ok(versions)
+}
+
+/// The version `current` points at, if any.
+pub fn current_version<T: Toolchain>() -> Result<Option<String>> {
+    let current = lang_dir::<T>()?.join(CURRENT_LINK);
+    Ok(fs::read_link(current)
+        .ok()
+        .and_then(|target| target.file_name().map(|n| n.to_string_lossy().to_string())))
+}
+
+/// Refuses versions that are not a single directory name (`3.12.1/../../x`, `/tmp`).
+fn check_version_name<T: Toolchain>(version: &str) -> Result<()> {
+    let mut components = Path::new(version).components();
+    let single = matches!((components.next(), components.next()), (Some(Component::Normal(_)), None));
+    if !single || version.contains(['/', '\\']) {
+        bail!(Error::new(
+            ErrorKind::InvalidInput,
+            format!("'{}' is not a valid {} version.", version, T::DISPLAY_NAME)
+        ));
+    }
+    Ok(())
+}
+
+/// Entries of a language directory that are not installed versions.
+fn is_reserved_dir(name: &str) -> bool {
+    name == CURRENT_LINK || staging::is_staging_dir(name) || name.starts_with('.')
+}
+
+/// Total size of the regular files below `path`. Symlinks are not followed.
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            Ok(t) if t.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// `YYYY-MM-DD` in UTC.
+pub fn format_date(time: SystemTime) -> String {
+    let mut formatted = humantime::format_rfc3339_seconds(time).to_string();
+    formatted.truncate(10);
+    formatted
+}
+
+/// Removes a symlink without touching what it points to.
+fn remove_link(link: &Path) {
+    if link.symlink_metadata().is_ok() {
+        // On Windows a directory symlink has to be removed with `remove_dir`.
+        if link.is_dir() {
+            fs::remove_dir(link).or_else(|_| fs::remove_file(link)).ok();
+        } else {
+            fs::remove_file(link).ok();
+        }
+    }
+}
+
+/// Points `current` at `target`, replacing whatever was there before.
+///
+/// The new link is created under a temporary name and renamed over `current`, so on
+/// Unix `current` is never missing, not even for an instant. Windows cannot rename
+/// over a directory link, so there the old one is removed first.
+fn switch_current(target: &Path, current: &Path) -> Result<()> {
+    let temp = current.with_file_name(format!(".{}-{}", CURRENT_LINK, std::process::id()));
+    remove_link(&temp);
+
+    let result = {
+        #[cfg(windows)]
+        {
+            std::os::windows::fs::symlink_dir(target, &temp)
+        }
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(target, &temp)
+        }
+    };
+
+    if let Err(e) = result {
+        #[cfg(windows)]
+        {
+            // `ErrorKind::PermissionDenied` is often what os error 1314 maps to.
+            if e.kind() == std::io::ErrorKind::PermissionDenied {
+                bail!(
+                    "Failed to create symlink. This is a permissions issue.\n\n\
+                    On Windows, you must either:\n\
+                    1. (Recommended) Enable 'Developer Mode' in your Windows settings.\n\
+                       Go to Settings > Update & Security > For Developers > and turn on 'Developer Mode'.\n\
+                    2. Run this command in a terminal that is 'Run as Administrator'.\n\n\
+                    Original error: {}",
+                    e
+                );
+            }
+        }
+        return Err(e).context("Failed to create symlink");
+    }
+
+    #[cfg(windows)]
+    remove_link(current);
+    fs::rename(&temp, current)
+        .inspect_err(|_| remove_link(&temp))
+        .context("Failed to replace the `current` link")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::node::Node;
+
+    #[test]
+    fn checksum_lookups_keep_the_kind_of_their_failure() {
+        let missing = checksum_lookup_failed(verify::no_entry("SHA256SUMS", "a.tar.gz").into(), "a.tar.gz");
+        assert_eq!(error::kind(&missing), ErrorKind::Checksum);
+        assert!(error::hint(&missing).is_some_and(|hint| hint.contains("--skip-verify")));
+
+        for kind in [ErrorKind::Network, ErrorKind::Signature, ErrorKind::Offline, ErrorKind::Other] {
+            let failed = checksum_lookup_failed(Error::new(kind, "503 Service Unavailable").into(), "a.tar.gz");
+            assert_eq!(error::kind(&failed), kind);
+        }
+    }
+
+    #[test]
+    fn relative_homes_are_made_absolute() {
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(absolute_home("relhome".into()), Some(cwd.join("relhome")));
+        let absolute = cwd.join("elsewhere");
+        assert_eq!(absolute_home(absolute.clone()), Some(absolute));
+        assert_eq!(absolute_home(PathBuf::new()), None);
+    }
+
+    #[test]
+    fn the_nearest_version_file_applies() {
+        let root = tempfile::tempdir().unwrap();
+        let nested = root.path().join("packages/app/src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.path().join(".node-version"), "# pinned by CI\n18.19.0\n").unwrap();
+
+        let found = project_version(&Node, &nested).unwrap();
+        assert_eq!((found.file, found.specs), (root.path().join(".node-version"), vec!["18.19.0".to_string()]));
+
+        // A closer file wins, and `.nvmrc` comes before `.node-version` in the same directory.
+        let app = root.path().join("packages/app");
+        fs::write(app.join(".node-version"), "20\n").unwrap();
+        fs::write(app.join(".nvmrc"), "v22.1.0 # nvm\n").unwrap();
+        let found = project_version(&Node, &nested).unwrap();
+        assert_eq!((found.file, found.specs), (app.join(".nvmrc"), vec!["v22.1.0".to_string()]));
+
+        // A file with nothing but comments is passed over.
+        fs::write(app.join(".nvmrc"), "# none\n\n").unwrap();
+        assert_eq!(project_version(&Node, &nested).unwrap().file, app.join(".node-version"));
+    }
+
+    #[test]
+    fn the_session_variable_beats_the_project_file() {
+        let project = tempfile::tempdir().unwrap();
+        fs::write(project.path().join(".node-version"), "20\n").unwrap();
+        let dir = Some(project.path());
+
+        let session = selection(&Node, Some("18:22".to_string()), dir);
+        assert!(matches!(session, Selection::Session(value) if value == "18:22"));
+        // An empty variable selects nothing, as if it were unset.
+        let blank = selection(&Node, Some(" ".to_string()), dir);
+        assert!(matches!(blank, Selection::Project(p) if p.specs == ["20"]));
+        assert!(matches!(selection(&Node, None, dir), Selection::Project(_)));
+
+        let elsewhere = tempfile::tempdir().unwrap();
+        assert!(matches!(selection(&Node, None, Some(elsewhere.path())), Selection::Current));
+        assert!(matches!(selection(&Node, None, None), Selection::Current));
+    }
+
+    #[test]
+    fn version_names_are_single_directories() {
+        assert!(check_version_name::<Node>("v20.11.0").is_ok());
+        assert!(check_version_name::<Node>("1.22rc1").is_ok());
+        for bad in ["", ".", "..", "3.12.1/../../victim", "../victim", "/tmp", "a/b", "a\\b"] {
+            let err = check_version_name::<Node>(bad).unwrap_err();
+            assert_eq!(error::kind(&err), ErrorKind::InvalidInput, "{bad}");
+        }
+    }
+}
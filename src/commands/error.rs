@@ -0,0 +1,122 @@
+// src/commands/error.rs
+//
+// Failures that callers need to tell apart. Errors still travel as
+// `anyhow::Error` with context attached; the ones that matter carry an
+// `Error` with a kind (which picks the exit code and is `error.kind` in JSON
+// output) and, where there is one, a hint telling the user what to do next.
+//
+// Exit codes are part of the interface and never change:
+//
+//   1  other             something not covered below
+//   2  invalid_input     bad arguments or settings (clap's usage errors also exit 2)
+//   3  not_found         no such version, alias or key
+//   4  not_installed     the version is not installed
+//   5  network           a download or request failed
+//   6  checksum          the checksum is missing or does not match
+//   7  signature         the signature of a checksum file did not verify
+//   8  offline           needs the network, but --offline was given
+//   9  locked            timed out waiting for another mlvm process
+//   10 unsupported_platform
+//   11 unsafe_archive    the archive tried to write outside its directory
+use serde::Serialize;
+use std::fmt;
+
+use crate::commands::http;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Other,
+    InvalidInput,
+    NotFound,
+    NotInstalled,
+    Network,
+    Checksum,
+    Signature,
+    Offline,
+    Locked,
+    UnsupportedPlatform,
+    UnsafeArchive,
+}
+
+impl ErrorKind {
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::InvalidInput => 2,
+            ErrorKind::NotFound => 3,
+            ErrorKind::NotInstalled => 4,
+            ErrorKind::Network => 5,
+            ErrorKind::Checksum => 6,
+            ErrorKind::Signature => 7,
+            ErrorKind::Offline => 8,
+            ErrorKind::Locked => 9,
+            ErrorKind::UnsupportedPlatform => 10,
+            ErrorKind::UnsafeArchive => 11,
+        }
+    }
+
+    /// What to suggest when the error itself has no hint.
+    fn default_hint(self) -> Option<&'static str> {
+        match self {
+            ErrorKind::Network => Some(
+                "Check your connection and proxy settings (HTTPS_PROXY, http.ca_bundle), \
+                or configure a mirror. Cached data is still usable with --offline.",
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// A failure of a known kind.
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    message: String,
+    hint: Option<String>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Error { kind, message: message.into(), hint: None }
+    }
+
+    /// A suggestion shown below the message, e.g. the command that fixes it.
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The kind of `error`: that of the outermost `Error` in it, else `Network` for
+/// failed requests, else `Other`.
+pub fn kind(error: &anyhow::Error) -> ErrorKind {
+    if let Some(e) = error.downcast_ref::<Error>() {
+        return e.kind;
+    }
+    let network = error
+        .chain()
+        .any(|cause| cause.is::<reqwest::Error>() || cause.is::<http::Stalled>());
+    if network { ErrorKind::Network } else { ErrorKind::Other }
+}
+
+/// The hint for `error`, if there is one.
+pub fn hint(error: &anyhow::Error) -> Option<String> {
+    error
+        .downcast_ref::<Error>()
+        .and_then(|e| e.hint.clone())
+        .or_else(|| kind(error).default_hint().map(str::to_string))
+}
+
+/// For the toolchains: no build exists for this OS or CPU.
+pub fn unsupported_platform(platform: String) -> Error {
+    Error::new(ErrorKind::UnsupportedPlatform, format!("Unsupported platform: {}", platform))
+}
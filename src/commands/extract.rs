@@ -0,0 +1,448 @@
+// src/commands/extract.rs
+//
+// Unpacks tar and zip archives from the network without trusting them. Every
+// entry is checked before anything is written:
+//
+// - its path must stay inside the destination (no `..`, no absolute paths,
+//   no writing through a symlink the archive created earlier);
+// - symlink and hardlink targets must point inside the destination. A symlink
+//   target may only start with `..`: after a name, `..` could climb out of a
+//   symlink that an entry created earlier (or creates later);
+// - the archive as a whole may not exceed `MAX_TOTAL_SIZE` or `MAX_ENTRIES`.
+//
+// Permissions are normalized: 0755 for directories and executables, 0644 for
+// everything else. The first rejected entry aborts the extraction and is named
+// in the error.
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use zip::ZipArchive;
+
+use crate::commands::engine;
+use crate::commands::error::{Error, ErrorKind};
+
+/// Upper bound for the unpacked size of one archive. Toolchains are a few hundred MB.
+const MAX_TOTAL_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+/// Upper bound for the number of entries in one archive.
+const MAX_ENTRIES: u64 = 200_000;
+
+/// Unix file mode bits of a symlink (`S_IFLNK`) as stored in zip entries.
+const S_IFLNK: u32 = 0o120000;
+const S_IFMT: u32 = 0o170000;
+
+/// Unpacks an (already decompressed) tar stream into `dest`.
+pub fn tar(reader: impl Read, dest: &Path) -> Result<()> {
+    tar_within(reader, dest, Budget::default())
+}
+
+fn tar_within(reader: impl Read, dest: &Path, mut budget: Budget) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+        let kind = entry.header().entry_type();
+        // Extension headers describe the next entry; the tar crate applies them for us.
+        if kind.is_pax_global_extensions() || kind.is_pax_local_extensions() {
+            continue;
+        }
+
+        let result = (|| -> Result<()> {
+            budget.add_entry()?;
+            let relative = safe_relative(&entry.path()?)?;
+            let target = dest.join(&relative);
+            ensure_no_symlink_ancestor(dest, &relative)?;
+
+            if kind.is_dir() {
+                create_dir(&target)
+            } else if kind.is_symlink() {
+                let link = entry.link_name()?.context("symlink without a target")?;
+                symlink(&relative, &link, &target)
+            } else if kind.is_hard_link() {
+                let link = entry.link_name()?.context("hardlink without a target")?;
+                let source = safe_relative(&link).context("hardlink target escapes the archive")?;
+                ensure_no_symlink_ancestor(dest, &source)?;
+                // A hardlink to a symlink is a copy of the symlink, which may escape from its new place.
+                if let Ok(link) = fs::read_link(dest.join(&source)) {
+                    check_symlink_target(&relative, &link)?;
+                }
+                replace_with(&target, |t| fs::hard_link(dest.join(&source), t))
+            } else if kind.is_file() || kind.is_gnu_sparse() {
+                let executable = entry.header().mode().unwrap_or(0o644) & 0o111 != 0;
+                let mtime = entry.header().mtime().ok();
+                write_file(&mut entry, &target, executable, &mut budget)?;
+                if let Some(mtime) = mtime {
+                    let file = fs::File::options().write(true).open(&target)?;
+                    file.set_modified(UNIX_EPOCH + Duration::from_secs(mtime)).ok();
+                }
+                Ok(())
+            } else {
+                bail!("unsupported entry type {:?}", kind)
+            }
+        })();
+        result.map_err(|e| rejected(e, &name))?;
+    }
+    Ok(())
+}
+
+/// Unpacks a zip archive into `dest`.
+pub fn zip(reader: impl Read + Seek, dest: &Path) -> Result<()> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut budget = Budget::default();
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let name = file.name().to_string();
+
+        let result = (|| -> Result<()> {
+            budget.add_entry()?;
+            // `enclosed_name` is `None` for absolute paths and `..` escapes.
+            let relative = safe_relative(file.enclosed_name().context("path escapes the destination")?)?;
+            let target = dest.join(&relative);
+            ensure_no_symlink_ancestor(dest, &relative)?;
+            let mode = file.unix_mode().unwrap_or(0o644);
+
+            if file.is_dir() {
+                create_dir(&target)
+            } else if mode & S_IFMT == S_IFLNK {
+                let mut link = String::new();
+                file.by_ref().take(4096).read_to_string(&mut link)?;
+                symlink(&relative, Path::new(&link), &target)
+            } else {
+                write_file(&mut file, &target, mode & 0o111 != 0, &mut budget)
+            }
+        })();
+        result.map_err(|e| rejected(e, &name))?;
+    }
+    Ok(())
+}
+
+/// Names the entry that failed. I/O errors are just that; anything else is the
+/// archive breaking a rule.
+fn rejected(e: anyhow::Error, name: &str) -> anyhow::Error {
+    let message = format!("Rejected archive entry '{}'", name);
+    if e.is::<io::Error>() {
+        e.context(message)
+    } else {
+        e.context(Error::new(ErrorKind::UnsafeArchive, message))
+    }
+}
+
+/// Running totals checked against the limits.
+struct Budget {
+    entries: u64,
+    bytes: u64,
+    max_entries: u64,
+    max_bytes: u64,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget { entries: 0, bytes: 0, max_entries: MAX_ENTRIES, max_bytes: MAX_TOTAL_SIZE }
+    }
+}
+
+impl Budget {
+    fn add_entry(&mut self) -> Result<()> {
+        self.entries += 1;
+        if self.entries > self.max_entries {
+            bail!("the archive has more than {} entries", self.max_entries);
+        }
+        Ok(())
+    }
+
+    /// What may still be written before the size limit is hit.
+    fn remaining(&self) -> u64 {
+        self.max_bytes - self.bytes
+    }
+
+    fn add_bytes(&mut self, n: u64) -> Result<()> {
+        self.bytes += n;
+        if self.bytes > self.max_bytes {
+            bail!(
+                "the archive unpacks to more than {}",
+                engine::format_size(self.max_bytes)
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Strips `.` components and rejects anything that is not a plain relative path.
+fn safe_relative(path: &Path) -> Result<PathBuf> {
+    let mut clean = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => clean.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => bail!("path contains '..'"),
+            Component::RootDir | Component::Prefix(_) => bail!("path is absolute"),
+        }
+    }
+    if clean.as_os_str().is_empty() {
+        bail!("path is empty");
+    }
+    Ok(clean)
+}
+
+/// Refuses to write below a symlink created by an earlier entry, which could lead
+/// anywhere on disk.
+fn ensure_no_symlink_ancestor(dest: &Path, relative: &Path) -> Result<()> {
+    let mut current = dest.to_path_buf();
+    let mut components = relative.components().peekable();
+    while let Some(component) = components.next() {
+        if components.peek().is_none() {
+            break;
+        }
+        current.push(component);
+        if fs::symlink_metadata(&current).is_ok_and(|m| m.file_type().is_symlink()) {
+            bail!("path goes through the symlink '{}'", current.display());
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `link`, stored at `relative`, resolves inside the destination, then creates it.
+fn symlink(relative: &Path, link: &Path, target: &Path) -> Result<()> {
+    check_symlink_target(relative, link)?;
+    replace_with(target, |t| {
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(link, t)
+        }
+        #[cfg(windows)]
+        {
+            let resolved = t.parent().map(|p| p.join(link)).unwrap_or_default();
+            if resolved.is_dir() {
+                std::os::windows::fs::symlink_dir(link, t)
+            } else {
+                std::os::windows::fs::symlink_file(link, t)
+            }
+        }
+    })
+}
+
+/// A symlink at `relative` may climb up with leading `..`s, as far as the destination,
+/// then only go down. Its parents are real directories, so the climb stays inside; every
+/// symlink it goes down through was held to the same rule.
+fn check_symlink_target(relative: &Path, link: &Path) -> Result<()> {
+    let mut depth = relative.components().count() - 1;
+    let mut descended = false;
+    for component in link.components() {
+        match component {
+            Component::Normal(_) => descended = true,
+            Component::CurDir => {}
+            Component::ParentDir if descended => {
+                bail!("symlink to '{}' has '..' after a name, which may climb out of a symlink", link.display())
+            }
+            Component::ParentDir if depth > 0 => depth -= 1,
+            Component::ParentDir => bail!("symlink to '{}' points outside the destination", link.display()),
+            Component::RootDir | Component::Prefix(_) => {
+                bail!("symlink to '{}' is absolute", link.display())
+            }
+        }
+    }
+    Ok(())
+}
+
+fn create_dir(target: &Path) -> Result<()> {
+    fs::create_dir_all(target)?;
+    set_mode(target, true)
+}
+
+/// Copies at most the remaining budget, so a lying size header cannot fill the disk.
+fn write_file(reader: &mut impl Read, target: &Path, executable: bool, budget: &mut Budget) -> Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Never write through whatever (e.g. a symlink) an earlier entry left at this path.
+    remove_existing(target)?;
+    let mut file = fs::File::create(target)?;
+    let written = io::copy(&mut reader.take(budget.remaining() + 1), &mut file)?;
+    budget.add_bytes(written)?;
+    drop(file);
+    set_mode(target, executable)
+}
+
+/// Creates a link at `target` via `create`, replacing a file an earlier entry put there.
+fn replace_with(target: &Path, create: impl FnOnce(&Path) -> io::Result<()>) -> Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    remove_existing(target)?;
+    create(target)?;
+    Ok(())
+}
+
+fn remove_existing(target: &Path) -> Result<()> {
+    match fs::symlink_metadata(target) {
+        Ok(m) if m.is_dir() => bail!("a directory already exists at this path"),
+        Ok(_) => fs::remove_file(target)?,
+        Err(_) => {}
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, executable: bool) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = if executable { 0o755 } else { 0o644 };
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _executable: bool) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::error;
+    use tar::EntryType;
+
+    /// Builds a tar archive entry by entry. Names are written raw, so entries can break
+    /// the rules the `tar` crate's own setters enforce.
+    #[derive(Default)]
+    struct TarBuilder(Vec<u8>);
+
+    impl TarBuilder {
+        fn entry(mut self, kind: EntryType, path: &str, link: &str, data: &[u8]) -> Self {
+            let mut header = tar::Header::new_gnu();
+            let gnu = header.as_gnu_mut().unwrap();
+            gnu.name[..path.len()].copy_from_slice(path.as_bytes());
+            gnu.linkname[..link.len()].copy_from_slice(link.as_bytes());
+            header.set_entry_type(kind);
+            header.set_mode(0o644);
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            self.0.extend_from_slice(header.as_bytes());
+            self.0.extend_from_slice(data);
+            self.0.resize(self.0.len().next_multiple_of(512), 0);
+            self
+        }
+
+        fn file(self, path: &str, data: &str) -> Self {
+            self.entry(EntryType::Regular, path, "", data.as_bytes())
+        }
+
+        fn dir(self, path: &str) -> Self {
+            self.entry(EntryType::Directory, path, "", b"")
+        }
+
+        fn symlink(self, path: &str, link: &str) -> Self {
+            self.entry(EntryType::Symlink, path, link, b"")
+        }
+
+        fn hardlink(self, path: &str, link: &str) -> Self {
+            self.entry(EntryType::Link, path, link, b"")
+        }
+
+        fn unpack_with(mut self, budget: Budget) -> (tempfile::TempDir, Result<()>) {
+            self.0.extend_from_slice(&[0; 1024]);
+            let dir = tempfile::tempdir().unwrap();
+            let dest = dir.path().join("dest");
+            fs::create_dir(&dest).unwrap();
+            let result = tar_within(&self.0[..], &dest, budget);
+            (dir, result)
+        }
+
+        fn unpack(self) -> (tempfile::TempDir, Result<()>) {
+            self.unpack_with(Budget::default())
+        }
+    }
+
+    fn assert_unsafe(result: Result<()>, entry: &str) {
+        let err = result.unwrap_err();
+        assert_eq!(error::kind(&err), ErrorKind::UnsafeArchive, "{err:#}");
+        assert!(err.to_string().contains(entry), "{err:#}");
+    }
+
+    #[test]
+    fn unpacks_files_links_and_directories() {
+        let (dir, result) = TarBuilder::default()
+            .dir("pkg/bin")
+            .file("pkg/lib/tool.js", "tool")
+            .symlink("pkg/bin/tool", "../lib/tool.js")
+            .hardlink("pkg/tool-copy.js", "pkg/lib/tool.js")
+            .unpack();
+        result.unwrap();
+        let pkg = dir.path().join("dest/pkg");
+        assert_eq!(fs::read_to_string(pkg.join("bin/tool")).unwrap(), "tool");
+        assert_eq!(fs::read_to_string(pkg.join("tool-copy.js")).unwrap(), "tool");
+    }
+
+    #[test]
+    fn rejects_parent_dir_traversal() {
+        let (dir, result) = TarBuilder::default().file("pkg/../../escaped", "x").unpack();
+        assert_unsafe(result, "pkg/../../escaped");
+        assert!(!dir.path().join("escaped").exists());
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        let (_dir, result) = TarBuilder::default().file("/tmp/mlvm-absolute", "x").unpack();
+        assert_unsafe(result, "/tmp/mlvm-absolute");
+
+        let (_dir, result) = TarBuilder::default().symlink("pkg/passwd", "/etc/passwd").unpack();
+        assert_unsafe(result, "pkg/passwd");
+    }
+
+    #[test]
+    fn rejects_symlinks_out_of_the_destination() {
+        let (_dir, result) = TarBuilder::default().symlink("pkg/up", "../..").unpack();
+        assert_unsafe(result, "pkg/up");
+    }
+
+    #[test]
+    fn rejects_symlink_chains_that_climb_out() {
+        // `a/l1` is the destination itself, so `a/l1/../..` is its parent.
+        let (_dir, result) = TarBuilder::default().dir("a").symlink("a/l1", "..").symlink("l2", "a/l1/../..").unpack();
+        assert_unsafe(result, "l2");
+
+        // The same with the links in the other order, when `a/l1` does not exist yet.
+        let (_dir, result) = TarBuilder::default().dir("a").symlink("l2", "a/l1/../..").symlink("a/l1", "..").unpack();
+        assert_unsafe(result, "l2");
+    }
+
+    #[test]
+    fn rejects_escaping_hardlinks() {
+        let (_dir, result) = TarBuilder::default().hardlink("pkg/passwd", "../../etc/passwd").unpack();
+        assert_unsafe(result, "pkg/passwd");
+
+        let (_dir, result) = TarBuilder::default().hardlink("pkg/passwd", "/etc/passwd").unpack();
+        assert_unsafe(result, "pkg/passwd");
+
+        // `a/b/up` is the destination; the same link one level up would be its parent.
+        let (_dir, result) = TarBuilder::default().dir("a/b").symlink("a/b/up", "../..").hardlink("up", "a/b/up").unpack();
+        assert_unsafe(result, "up");
+    }
+
+    #[test]
+    fn rejects_writes_through_a_symlink() {
+        let (dir, result) = TarBuilder::default().dir("real").symlink("link", "real").file("link/file", "x").unpack();
+        assert_unsafe(result, "link/file");
+        assert!(!dir.path().join("dest/real/file").exists());
+
+        let (_dir, result) = TarBuilder::default()
+            .file("real/file", "x")
+            .symlink("link", "real")
+            .hardlink("copy", "link/file")
+            .unpack();
+        assert_unsafe(result, "copy");
+    }
+
+    #[test]
+    fn enforces_the_size_and_entry_limits() {
+        let budget = Budget { max_bytes: 8, ..Budget::default() };
+        let (_dir, result) = TarBuilder::default().file("small", "1234").file("big", "56789").unpack_with(budget);
+        assert_unsafe(result, "big");
+
+        let budget = Budget { max_entries: 2, ..Budget::default() };
+        let (_dir, result) = TarBuilder::default().dir("a").file("a/b", "").file("a/c", "").unpack_with(budget);
+        assert_unsafe(result, "a/c");
+    }
+}
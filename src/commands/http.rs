@@ -0,0 +1,313 @@
+// src/commands/http.rs
+//
+// Every HTTP request mlvm makes goes through here, so transient failures
+// (connection resets, timeouts, 5xx, 429) are retried with exponential
+// backoff instead of failing the whole command.
+//
+// All requests share one client. It uses the proxy from `HTTPS_PROXY`,
+// `HTTP_PROXY` or `ALL_PROXY` (except for hosts in `NO_PROXY`), trusts an
+// extra CA bundle when one is configured, and sends `GITHUB_TOKEN` to
+// api.github.com, which otherwise allows only 60 requests an hour.
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+use crate::commands::config;
+use crate::commands::error::{Error, ErrorKind};
+use crate::commands::output::status;
+
+/// Total tries per request, including the first one.
+pub const MAX_ATTEMPTS: u32 = 5;
+/// The longest we ever wait between two attempts, also caps `Retry-After`.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How long to wait for a connection when `http.connect_timeout` is not set.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a request may go without receiving anything when `http.timeout` is not set.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+/// The only host that gets the GitHub token; mirrors of it never do.
+const GITHUB_API_HOST: &str = "api.github.com";
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+static OFFLINE: OnceLock<bool> = OnceLock::new();
+static TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// Called once from `main` with the value of `--offline` / `MLVM_OFFLINE`.
+pub fn set_offline(offline: bool) {
+    OFFLINE.set(offline).ok();
+}
+
+/// Whether the network must not be used at all.
+pub fn is_offline() -> bool {
+    OFFLINE.get().copied().unwrap_or(false)
+}
+
+/// The client shared by every request, built from the `http.*` settings on first use.
+pub fn client() -> Result<Client> {
+    if let Some(client) = CLIENT.get() {
+        return Ok(client.clone());
+    }
+    let config = config::load()?;
+    let duration = |value: &Option<String>| value.as_deref().map(config::parse_duration).transpose();
+    let connect_timeout = duration(&config.http.connect_timeout)?;
+    TIMEOUT.set(duration(&config.http.timeout)?.unwrap_or(DEFAULT_TIMEOUT)).ok();
+
+    // GitHub's API rejects requests without a user agent.
+    let mut builder = Client::builder()
+        .user_agent("mlvm-rust")
+        .connect_timeout(connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT));
+    let ca_bundle = env::var_os("MLVM_CA_BUNDLE")
+        .filter(|v| !v.is_empty())
+        .map(Into::into)
+        .or(config.http.ca_bundle);
+    if let Some(path) = ca_bundle {
+        let pem = fs::read(&path)
+            .with_context(|| format!("Failed to read the CA bundle {}", path.display()))?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid CA bundle {}", path.display()))?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    let client = builder.build()?;
+    Ok(CLIENT.get_or_init(|| client).clone())
+}
+
+/// `http.timeout`: the longest a request may wait for the server.
+pub fn timeout() -> Duration {
+    TIMEOUT.get().copied().unwrap_or(DEFAULT_TIMEOUT)
+}
+
+/// A `GET url` request with the GitHub token attached when `url` is GitHub's API.
+pub fn request(url: &str) -> Result<RequestBuilder> {
+    Ok(authorize(client()?.get(url), url, github_token()))
+}
+
+/// Adds `token` to a request for `url`, but only if `url` is GitHub's API.
+fn authorize(request: RequestBuilder, url: &str, token: Option<String>) -> RequestBuilder {
+    match token {
+        Some(token) if is_github_api(url) => request.header(AUTHORIZATION, format!("Bearer {}", token)),
+        _ => request,
+    }
+}
+
+/// `GET url`, retrying transient failures. The whole request (body included) must
+/// finish within `timeout()`; archives use `request` and `next_chunk` instead.
+pub async fn get(url: &str) -> Result<Response> {
+    get_with_headers(url, HeaderMap::new()).await
+}
+
+/// `get` with extra request headers, e.g. for conditional requests.
+pub async fn get_with_headers(url: &str, headers: HeaderMap) -> Result<Response> {
+    send(request(url)?.headers(headers).timeout(timeout())).await
+}
+
+/// The next piece of a streamed body. A server that sends nothing for `timeout()`
+/// counts as a dropped connection.
+pub async fn next_chunk(response: &mut Response) -> Result<Option<Bytes>> {
+    match tokio::time::timeout(timeout(), response.chunk()).await {
+        Ok(chunk) => Ok(chunk?),
+        Err(_) => Err(Stalled(timeout()).into()),
+    }
+}
+
+/// A download that stopped sending data.
+#[derive(Debug)]
+pub struct Stalled(Duration);
+
+impl std::fmt::Display for Stalled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no data received for {}s", self.0.as_secs())
+    }
+}
+
+impl std::error::Error for Stalled {}
+
+/// `MLVM_GITHUB_TOKEN`, else `GITHUB_TOKEN` (as set in GitHub Actions).
+fn github_token() -> Option<String> {
+    ["MLVM_GITHUB_TOKEN", "GITHUB_TOKEN"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|v| !v.trim().is_empty()))
+        .map(|v| v.trim().to_string())
+}
+
+fn is_github_api(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|u| u.scheme() == "https" && u.host_str() == Some(GITHUB_API_HOST))
+}
+
+/// Sends `request`, retrying transient failures. The final response is returned as is
+/// (callers still call `error_for_status`), so a 404 surfaces as a 404.
+pub async fn send(request: RequestBuilder) -> Result<Response> {
+    if is_offline() {
+        let url = request.try_clone().and_then(|r| r.build().ok()).map(|r| r.url().to_string());
+        bail!(Error::new(
+            ErrorKind::Offline,
+            format!("{} is not available with --offline", url.as_deref().unwrap_or("The network"))
+        )
+        .hint("Run again without --offline (and unset MLVM_OFFLINE) to go online."));
+    }
+    let mut attempt = 1;
+    loop {
+        let this_try = request
+            .try_clone()
+            .context("Request body cannot be retried")?;
+
+        // Also bounds requests without a body timeout, i.e. archive downloads.
+        let sent = match tokio::time::timeout(timeout(), this_try.send()).await {
+            Ok(sent) => sent.map_err(anyhow::Error::from),
+            Err(_) => Err(Stalled(timeout()).into()),
+        };
+
+        let wait = match sent {
+            Ok(response) if is_retryable_status(response.status()) && attempt < MAX_ATTEMPTS => {
+                let wait = retry_after(&response).unwrap_or_else(|| backoff(attempt));
+                status!(
+                    "{} returned {}, retrying in {}s ({}/{})...",
+                    response.url(),
+                    response.status(),
+                    wait.as_secs(),
+                    attempt,
+                    MAX_ATTEMPTS - 1
+                );
+                wait
+            }
+            Ok(response) => return check_rate_limit(response),
+            Err(e) if is_transient_error(&e) && attempt < MAX_ATTEMPTS => {
+                let wait = backoff(attempt);
+                status!(
+                    "Request failed ({}), retrying in {}s ({}/{})...",
+                    e,
+                    wait.as_secs(),
+                    attempt,
+                    MAX_ATTEMPTS - 1
+                );
+                wait
+            }
+            Err(e) => return Err(e),
+        };
+
+        tokio::time::sleep(wait).await;
+        attempt += 1;
+    }
+}
+
+/// 1s, 2s, 4s, ... capped at `MAX_BACKOFF`.
+pub fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1u64 << attempt.saturating_sub(1).min(5)).min(MAX_BACKOFF)
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Network-level failures that are worth another try.
+pub fn is_transient(e: &reqwest::Error) -> bool {
+    e.is_connect() || e.is_timeout() || e.is_request() || e.is_body()
+}
+
+/// `is_transient` for errors from `next_chunk` and the like, which includes stalls.
+pub fn is_transient_error(e: &anyhow::Error) -> bool {
+    e.is::<Stalled>() || e.downcast_ref::<reqwest::Error>().is_some_and(is_transient)
+}
+
+/// Turns GitHub's rate limit response into an error that says what to do about it.
+fn check_rate_limit(response: Response) -> Result<Response> {
+    let limited = matches!(response.status(), StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS)
+        && response.url().host_str() == Some(GITHUB_API_HOST)
+        && response
+            .headers()
+            .get("x-ratelimit-remaining")
+            .is_some_and(|v| v.as_bytes() == b"0");
+    if !limited {
+        return Ok(response);
+    }
+    if github_token().is_some() {
+        bail!(Error::new(ErrorKind::Network, "The GitHub API rate limit for your token is used up.")
+            .hint("Try again later."));
+    }
+    bail!(Error::new(
+        ErrorKind::Network,
+        "The GitHub API allows 60 requests an hour without a token and they are used up."
+    )
+    .hint("Set GITHUB_TOKEN or MLVM_GITHUB_TOKEN to a personal access token to raise the limit."))
+}
+
+/// `Retry-After` as either delay-seconds or an HTTP date.
+fn retry_after(response: &Response) -> Option<Duration> {
+    parse_retry_after(response.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?)
+}
+
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let wait = match value.trim().parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()?
+            .duration_since(SystemTime::now())
+            .unwrap_or_default(),
+    };
+    Some(wait.min(MAX_BACKOFF))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_support::{self, Response};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn waits_grow_but_stay_capped() {
+        let waits: Vec<u64> = (1..=7).map(|attempt| backoff(attempt).as_secs()).collect();
+        assert_eq!(waits, [1, 2, 4, 8, 16, 30, 30]);
+
+        assert_eq!(parse_retry_after("5"), Some(Duration::from_secs(5)));
+        // A server cannot make us wait longer than our own backoff would.
+        assert_eq!(parse_retry_after("86400"), Some(MAX_BACKOFF));
+        let tomorrow = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(86400));
+        assert_eq!(parse_retry_after(&tomorrow), Some(MAX_BACKOFF));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn the_token_only_goes_to_the_github_api() {
+        let token = || Some("secret".to_string());
+        let sent = |url: &str, token: Option<String>| {
+            let request = authorize(Client::new().get(url), url, token).build().unwrap();
+            request.headers().get(AUTHORIZATION).map(|v| v.to_str().unwrap().to_string())
+        };
+        assert_eq!(sent("https://api.github.com/repos/oven-sh/bun/releases", token()).as_deref(), Some("Bearer secret"));
+        assert_eq!(sent("https://api.github.com/repos/oven-sh/bun/releases", None), None);
+        for url in [
+            "http://api.github.com/repos/oven-sh/bun/releases",
+            "https://github.com/oven-sh/bun/releases/download/bun-v1.0.0/bun-linux-x64.zip",
+            "https://api.github.com.example.com/repos",
+            "https://mirror.example.com/api.github.com/repos",
+        ] {
+            assert_eq!(sent(url, token()), None, "{url}");
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_what_the_server_asks_to_retry() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let seen = hits.clone();
+        let url = test_support::serve(move |_| match seen.fetch_add(1, Ordering::SeqCst) {
+            0 => Response::new(503, "busy").header("Retry-After", "0"),
+            1 => Response::new(429, "slow down").header("Retry-After", "0"),
+            _ => Response::new(200, "ok"),
+        });
+        let response = get(&url).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+        // Anything else is the caller's to handle.
+        let url = test_support::serve(|_| Response::new(404, "missing"));
+        assert_eq!(get(&url).await.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,289 @@
+// src/commands/index.rs
+//
+// Remote version indexes (Node's `index.json`, go.dev's JSON, GitHub release
+// listings) are kept in `~/.mlvm/cache/index/<lang>/`. A copy younger than
+// `cache.index_ttl` is used as is; an older one is revalidated with
+// `If-None-Match` / `If-Modified-Since`, so an unchanged index costs a 304.
+// With `--offline` only the cached copy is used, however old it is.
+use anyhow::{bail, Context, Result};
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::commands::cache;
+use crate::commands::config;
+use crate::commands::engine;
+use crate::commands::error::{Error, ErrorKind};
+use crate::commands::http;
+use crate::commands::mirror::{self, Endpoint};
+use crate::commands::output::status;
+use crate::commands::toolchain::Toolchain;
+
+/// How long a cached index is used without asking the server when `cache.index_ttl` is not set.
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize, Deserialize, Debug)]
+struct IndexRecord {
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    /// Seconds since the Unix epoch of the last download or successful revalidation.
+    fetched_at: u64,
+}
+
+/// A cached index: the body plus the record describing it.
+struct Cached {
+    body: PathBuf,
+    record_path: PathBuf,
+    record: IndexRecord,
+}
+
+/// `path` on the index endpoint of `T` (see `commands::mirror`), parsed as JSON.
+pub async fn json<T: Toolchain, D: DeserializeOwned>(path: &str) -> Result<D> {
+    let (body, source) = fetch::<T>(path).await?;
+    serde_json::from_slice(&body).with_context(|| format!("Invalid {} version index from {}", T::DISPLAY_NAME, source))
+}
+
+/// The body of the index at `path`, and where it came from.
+async fn fetch<T: Toolchain>(path: &str) -> Result<(Vec<u8>, String)> {
+    let urls = mirror::candidates::<T>(&mirror::url::<T>(Endpoint::Index, path)?)?;
+    let (body_path, record_path) = paths::<T>(path)?;
+    let ttl = match http::is_offline() {
+        true => None,
+        false => Some(config::load()?.index_ttl()?.unwrap_or(DEFAULT_TTL)),
+    };
+    fetch_from::<T>(&urls, body_path, record_path, ttl).await
+}
+
+/// `fetch` against the mirrors `urls`, caching in `body_path` and `record_path`.
+/// `ttl` is `None` when offline.
+async fn fetch_from<T: Toolchain>(
+    urls: &[String],
+    body_path: PathBuf,
+    record_path: PathBuf,
+    ttl: Option<Duration>,
+) -> Result<(Vec<u8>, String)> {
+    let cached = read_cached(body_path.clone(), record_path.clone());
+
+    let Some(ttl) = ttl else {
+        let Some(cached) = cached else {
+            bail!(Error::new(
+                ErrorKind::Offline,
+                format!("No cached {} version index.", T::DISPLAY_NAME)
+            )
+            .hint(format!("Run `mlvm {} list-remote` once without --offline.", T::NAME)));
+        };
+        return Ok((fs::read(&cached.body)?, format!("{} (cached)", cached.record.url)));
+    };
+
+    if let Some(cached) = &cached
+        && now().saturating_sub(cached.record.fetched_at) < ttl.as_secs()
+        && let Ok(body) = fs::read(&cached.body)
+    {
+        return Ok((body, format!("{} (cached)", cached.record.url)));
+    }
+
+    let mut headers = HeaderMap::new();
+    if let Some(cached) = &cached {
+        if let Some(etag) = cached.record.etag.as_deref().and_then(|v| v.parse().ok()) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(date) = cached.record.last_modified.as_deref().and_then(|v| v.parse().ok()) {
+            headers.insert(IF_MODIFIED_SINCE, date);
+        }
+    }
+
+    let response = match mirror::first_answer(urls, headers).await {
+        Ok(response) => response,
+        Err(e) => return stale_or(cached, e),
+    };
+    let source = response.url().to_string();
+
+    if response.status() == StatusCode::NOT_MODIFIED
+        && let Some(mut cached) = cached
+    {
+        cached.record.fetched_at = now();
+        fs::write(&cached.record_path, serde_json::to_vec_pretty(&cached.record)?).ok();
+        return Ok((fs::read(&cached.body)?, source));
+    }
+    let response = match response.error_for_status() {
+        Ok(response) => response,
+        Err(e) => return stale_or(cached, e.into()),
+    };
+
+    let headers = response.headers();
+    let record = IndexRecord {
+        url: source.clone(),
+        etag: headers.get(ETAG).and_then(|v| v.to_str().ok()).map(str::to_string),
+        last_modified: headers.get(LAST_MODIFIED).and_then(|v| v.to_str().ok()).map(str::to_string),
+        fetched_at: now(),
+    };
+    let body = response.bytes().await?.to_vec();
+    // A read-only mlvm home still works, just without the cache.
+    save(&body_path, &record_path, &record, &body).ok();
+    Ok((body, source))
+}
+
+/// When the server cannot be reached, an old index beats none at all.
+fn stale_or(cached: Option<Cached>, error: anyhow::Error) -> Result<(Vec<u8>, String)> {
+    let Some(cached) = cached else {
+        return Err(error);
+    };
+    let Ok(body) = fs::read(&cached.body) else {
+        return Err(error);
+    };
+    status!(
+        "Could not refresh the version index ({:#}), using the copy from {}.",
+        error,
+        engine::format_date(UNIX_EPOCH + Duration::from_secs(cached.record.fetched_at))
+    );
+    Ok((body, format!("{} (cached)", cached.record.url)))
+}
+
+/// `~/.mlvm/cache/index/<lang>`
+fn dir<T: Toolchain>() -> Result<PathBuf> {
+    Ok(cache::cache_dir()?.join("index").join(T::NAME))
+}
+
+/// The body and record files for `path`. The key leaves out the mirror, so switching
+/// mirrors keeps the cache.
+fn paths<T: Toolchain>(path: &str) -> Result<(PathBuf, PathBuf)> {
+    let key = format!("{:x}", Sha256::digest(path.as_bytes()));
+    let dir = dir::<T>()?;
+    Ok((dir.join(format!("{}.body", &key[..16])), dir.join(format!("{}.json", &key[..16]))))
+}
+
+fn read_cached(body: PathBuf, record_path: PathBuf) -> Option<Cached> {
+    let record = serde_json::from_slice(&fs::read(&record_path).ok()?).ok()?;
+    body.is_file().then_some(Cached { body, record_path, record })
+}
+
+/// Writes the body before the record, so a record never describes a partial body.
+fn save(body_path: &Path, record_path: &Path, record: &IndexRecord, body: &[u8]) -> Result<()> {
+    if let Some(parent) = body_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::remove_file(record_path).ok();
+    let part = body_path.with_extension("part");
+    fs::write(&part, body)?;
+    fs::rename(&part, body_path)?;
+    fs::write(record_path, serde_json::to_vec_pretty(record)?)?;
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::node::Node;
+    use crate::commands::test_support::{self, Response};
+    use std::sync::{Arc, Mutex};
+
+    /// The `If-None-Match` header of each request, in order.
+    type Seen = Arc<Mutex<Vec<Option<String>>>>;
+
+    /// A server whose index body and ETag are `current`.
+    fn index_server(current: Arc<Mutex<(&'static str, &'static str)>>) -> (Vec<String>, Seen) {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let base = test_support::serve(move |request| {
+            let (body, etag) = *current.lock().unwrap();
+            let sent = request.headers.get("if-none-match").cloned();
+            let unchanged = sent.as_deref() == Some(etag);
+            log.lock().unwrap().push(sent);
+            match unchanged {
+                true => Response::new(304, ""),
+                false => Response::new(200, body).header("ETag", etag),
+            }
+        });
+        (vec![format!("{}/index.json", base)], seen)
+    }
+
+    fn cache_files(dir: &Path) -> (PathBuf, PathBuf) {
+        (dir.join("index.body"), dir.join("index.json"))
+    }
+
+    fn age(record_path: &Path, seconds: u64) {
+        let mut record: IndexRecord = serde_json::from_slice(&fs::read(record_path).unwrap()).unwrap();
+        record.fetched_at -= seconds;
+        fs::write(record_path, serde_json::to_vec(&record).unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_fresh_index_is_used_without_asking() {
+        let dir = tempfile::tempdir().unwrap();
+        let (body, record) = cache_files(dir.path());
+        let current = Arc::new(Mutex::new(("[1]", "\"a\"")));
+        let (urls, seen) = index_server(current);
+        let ttl = Some(Duration::from_secs(60));
+
+        let (first, _) = fetch_from::<Node>(&urls, body.clone(), record.clone(), ttl).await.unwrap();
+        let (second, source) = fetch_from::<Node>(&urls, body, record, ttl).await.unwrap();
+        assert_eq!((first.as_slice(), second.as_slice()), (&b"[1]"[..], &b"[1]"[..]));
+        assert!(source.ends_with("(cached)"));
+        assert_eq!(*seen.lock().unwrap(), [None]);
+    }
+
+    #[tokio::test]
+    async fn an_expired_index_is_revalidated() {
+        let dir = tempfile::tempdir().unwrap();
+        let (body, record) = cache_files(dir.path());
+        let current = Arc::new(Mutex::new(("[1]", "\"a\"")));
+        let (urls, seen) = index_server(current.clone());
+        let ttl = Some(Duration::from_secs(60));
+
+        fetch_from::<Node>(&urls, body.clone(), record.clone(), ttl).await.unwrap();
+        age(&record, 120);
+        let (unchanged, _) = fetch_from::<Node>(&urls, body.clone(), record.clone(), ttl).await.unwrap();
+        assert_eq!(unchanged, b"[1]");
+        // The 304 restarts the TTL, so the next call does not ask again.
+        fetch_from::<Node>(&urls, body.clone(), record.clone(), ttl).await.unwrap();
+        assert_eq!(*seen.lock().unwrap(), [None, Some("\"a\"".to_string())]);
+
+        *current.lock().unwrap() = ("[1,2]", "\"b\"");
+        age(&record, 120);
+        let (changed, _) = fetch_from::<Node>(&urls, body.clone(), record.clone(), ttl).await.unwrap();
+        assert_eq!(changed, b"[1,2]");
+        assert_eq!(fs::read(&body).unwrap(), b"[1,2]");
+    }
+
+    #[tokio::test]
+    async fn offline_uses_the_cache_however_old() {
+        let dir = tempfile::tempdir().unwrap();
+        let (body, record) = cache_files(dir.path());
+        let current = Arc::new(Mutex::new(("[1]", "\"a\"")));
+        let (urls, seen) = index_server(current);
+
+        fetch_from::<Node>(&urls, body.clone(), record.clone(), Some(Duration::from_secs(60))).await.unwrap();
+        age(&record, 365 * 24 * 60 * 60);
+        let (cached, _) = fetch_from::<Node>(&urls, body, record, None).await.unwrap();
+        assert_eq!(cached, b"[1]");
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn offline_without_a_cache_fails_cleanly() {
+        let dir = tempfile::tempdir().unwrap();
+        let (body, record) = cache_files(dir.path());
+        let current = Arc::new(Mutex::new(("[1]", "\"a\"")));
+        let (urls, seen) = index_server(current);
+
+        let e = fetch_from::<Node>(&urls, body.clone(), record, None).await.unwrap_err();
+        assert_eq!(crate::commands::error::kind(&e), ErrorKind::Offline);
+        assert!(seen.lock().unwrap().is_empty());
+        assert!(!body.exists());
+    }
+}
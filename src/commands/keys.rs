@@ -0,0 +1,707 @@
+// src/commands/keys.rs
+//
+// OpenPGP verification of upstream checksum files. Signatures are checked in
+// process (rPGP), so no GnuPG install is needed, against a keyring that belongs
+// to mlvm alone: `~/.mlvm/keys/<lang>/*.gpg`, one binary key file per trusted key.
+// Only keys in that directory are trusted; the user's own GnuPG setup is never used.
+//
+// The upstream release keys are built into mlvm (`keys/<lang>.asc`, rebuilt by
+// `keys/update.sh`), so filling the keyring needs no network. Only a release key
+// missing from that bundle is looked up on a keyserver.
+use anyhow::{bail, Context, Result};
+use pgp::composed::{CleartextSignedMessage, Deserializable, DetachedSignature, SignedPublicKey};
+use pgp::packet::{Signature, SignatureType};
+use pgp::ser::Serialize as _;
+use pgp::types::{KeyDetails, Tag, Timestamp};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{KeysCommand, SignedLanguage};
+use crate::commands::bun::Bun;
+use crate::commands::engine;
+use crate::commands::error::{Error, ErrorKind};
+use crate::commands::http;
+use crate::commands::mirror;
+use crate::commands::node::Node;
+use crate::commands::output::{self, status};
+use crate::commands::toolchain::Toolchain;
+use crate::commands::verify;
+
+/// Where `mlvm keys update` looks for a release key that is not built in, in order. Every
+/// key is checked against the fingerprint it was asked for, so a keyserver is not trusted.
+const KEYSERVER_URLS: [&str; 2] = [
+    "https://keys.openpgp.org/vks/v1/by-fingerprint/",
+    "https://keyserver.ubuntu.com/pks/lookup?op=get&options=mr&search=0x",
+];
+
+/// The trusted keys of one language.
+#[derive(Serialize, Debug)]
+pub struct Keyring {
+    pub language: &'static str,
+    #[serde(skip)]
+    display_name: &'static str,
+    /// Whether `mlvm keys update` has keys to fetch for this language.
+    #[serde(skip)]
+    ships_keys: bool,
+    pub dir: PathBuf,
+    pub keys: Vec<TrustedKey>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TrustedKey {
+    /// The file name without `.gpg`: a fingerprint for built-in keys.
+    pub name: String,
+    pub builtin: bool,
+}
+
+/// Keys added by `update` or `import`, or dropped by `remove`.
+#[derive(Serialize, Debug)]
+pub struct KeyChange {
+    pub language: &'static str,
+    #[serde(skip)]
+    display_name: &'static str,
+    pub keys: Vec<String>,
+}
+
+pub async fn run(command: KeysCommand) -> Result<()> {
+    match command {
+        KeysCommand::List => {
+            let keyrings = vec![keyring::<Node>()?, keyring::<Bun>()?];
+            output::emit(&keyrings, |keyrings| keyrings.iter().for_each(print_keyring))
+        }
+        KeysCommand::Update { language } => {
+            let changes = match language {
+                Some(SignedLanguage::Node) => vec![update::<Node>().await?],
+                Some(SignedLanguage::Bun) => vec![update::<Bun>().await?],
+                None => vec![update::<Node>().await?, update::<Bun>().await?],
+            };
+            // `update` reports every key as it arrives.
+            output::emit(&changes, |_| {})
+        }
+        KeysCommand::Import { language, source } => {
+            let change = match language {
+                SignedLanguage::Node => import::<Node>(&source).await?,
+                SignedLanguage::Bun => import::<Bun>(&source).await?,
+            };
+            output::emit(&change, |c| println!("Trusted {} key '{}'.", c.display_name, c.keys[0]))
+        }
+        KeysCommand::Remove { language, name } => {
+            let change = match language {
+                SignedLanguage::Node => remove::<Node>(&name)?,
+                SignedLanguage::Bun => remove::<Bun>(&name)?,
+            };
+            output::emit(&change, |c| println!("Removed {} key '{}'.", c.display_name, c.keys[0]))
+        }
+    }
+}
+
+/// `~/.mlvm/keys/<lang>`
+fn keyring_dir<T: Toolchain>() -> Result<PathBuf> {
+    Ok(engine::mlvm_dir()?.join("keys").join(T::NAME))
+}
+
+fn keyring_files<T: Toolchain>() -> Result<Vec<PathBuf>> {
+    let dir = keyring_dir::<T>()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "gpg"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+pub fn keyring<T: Toolchain>() -> Result<Keyring> {
+    let keys = keyring_files::<T>()?
+        .into_iter()
+        .map(|file| {
+            let name = file.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let builtin = T::RELEASE_KEYS.iter().any(|fpr| fpr.eq_ignore_ascii_case(&name));
+            TrustedKey { name, builtin }
+        })
+        .collect();
+    Ok(Keyring {
+        language: T::NAME,
+        display_name: T::DISPLAY_NAME,
+        ships_keys: !T::RELEASE_KEYS.is_empty(),
+        dir: keyring_dir::<T>()?,
+        keys,
+    })
+}
+
+fn print_keyring(keyring: &Keyring) {
+    println!("Trusted {} keys ({}):", keyring.display_name, keyring.dir.display());
+    if keyring.keys.is_empty() {
+        if keyring.ships_keys {
+            println!("  (none yet, run `mlvm keys update {}`)", keyring.language);
+        } else {
+            println!("  (none, import one with `mlvm keys import {} <file|url>`)", keyring.language);
+        }
+    }
+    for key in &keyring.keys {
+        println!("- {}{}", key.name, if key.builtin { " (built-in)" } else { "" });
+    }
+}
+
+/// Adds every built-in release key of a language to its keyring.
+pub async fn update<T: Toolchain>() -> Result<KeyChange> {
+    let mut change = KeyChange { language: T::NAME, display_name: T::DISPLAY_NAME, keys: Vec::new() };
+    if T::RELEASE_KEYS.is_empty() {
+        status!(
+            "mlvm ships no {} release keys. Use `mlvm keys import {} <file|url>`.",
+            T::DISPLAY_NAME,
+            T::NAME
+        );
+        return Ok(change);
+    }
+
+    let dir = keyring_dir::<T>()?;
+    fs::create_dir_all(&dir)?;
+    status!("Adding {} {} release keys...", T::RELEASE_KEYS.len(), T::DISPLAY_NAME);
+    let bundled = bundled_keys::<T>()?;
+    for fingerprint in T::RELEASE_KEYS {
+        let key = match find_key(&bundled, fingerprint) {
+            Some(key) => key.clone(),
+            None => fetch_release_key(fingerprint).await?,
+        };
+        fs::write(dir.join(format!("{}.gpg", fingerprint)), key.to_bytes()?)?;
+        status!("- {}", fingerprint);
+        change.keys.push(fingerprint.to_string());
+    }
+    Ok(change)
+}
+
+/// The release keys built into mlvm for a language.
+fn bundled_keys<T: Toolchain>() -> Result<Vec<SignedPublicKey>> {
+    parse_bundle(T::RELEASE_KEYRING, T::RELEASE_KEYS)
+        .with_context(|| format!("The built-in {} keys are invalid", T::DISPLAY_NAME))
+}
+
+/// Parses a `keys/<lang>.asc` bundle. Only a language without `release_keys` may have a
+/// bundle without keys.
+fn parse_bundle(bundle: &[u8], release_keys: &[&str]) -> Result<Vec<SignedPublicKey>> {
+    if !String::from_utf8_lossy(bundle).contains(ARMOR_BEGIN) {
+        if release_keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        bail!(Error::new(ErrorKind::Signature, "This mlvm was built without its release keys.")
+            .hint("Rebuild mlvm after running keys/update.sh, or import the keys with `mlvm keys import`."));
+    }
+    parse_keys(bundle)
+}
+
+fn find_key<'a>(keys: &'a [SignedPublicKey], fingerprint: &str) -> Option<&'a SignedPublicKey> {
+    keys.iter().find(|key| key_fingerprint(key).eq_ignore_ascii_case(fingerprint))
+}
+
+/// Fetches the key with `fingerprint` from the first keyserver that has it.
+async fn fetch_release_key(fingerprint: &str) -> Result<SignedPublicKey> {
+    let mut last_error = None;
+    for server in KEYSERVER_URLS {
+        let url = format!("{}{}", server, fingerprint);
+        let response = match http::get(&url).await.and_then(|r| Ok(r.error_for_status()?)) {
+            Ok(response) => response,
+            Err(e) => {
+                last_error = Some(e.context(format!("Failed to fetch key {} from {}", fingerprint, url)));
+                continue;
+            }
+        };
+        let keys = parse_keys(&response.bytes().await?)?;
+        // Whatever the server sent, only the key that was asked for is kept.
+        return match find_key(&keys, fingerprint) {
+            Some(key) => Ok(key.clone()),
+            None => bail!(Error::new(
+                ErrorKind::Signature,
+                format!("{} did not return key {}; refusing to trust what it sent instead.", url, fingerprint)
+            )),
+        };
+    }
+    let error = Error::new(
+        ErrorKind::Network,
+        format!("Release key {} is not built in and no keyserver could be reached.", fingerprint),
+    )
+    .hint("Check your connection, or import the key with `mlvm keys import <language> <file>`.");
+    Err(match last_error {
+        Some(e) => e.context(error),
+        None => error.into(),
+    })
+}
+
+/// Trusts the public key(s) in a local file or at an HTTP(S) URL.
+pub async fn import<T: Toolchain>(source: &str) -> Result<KeyChange> {
+    let bytes = if source.starts_with("http://") || source.starts_with("https://") {
+        http::get(source).await?.error_for_status()?.bytes().await?.to_vec()
+    } else {
+        fs::read(source).with_context(|| format!("Could not read {}", source))?
+    };
+    let mut key = Vec::new();
+    for parsed in parse_keys(&bytes)? {
+        key.extend(parsed.to_bytes()?);
+    }
+
+    let name = Path::new(source.trim_end_matches('/'))
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "imported".to_string());
+    check_key_name::<T>(&name)?;
+
+    let dir = keyring_dir::<T>()?;
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(format!("{}.gpg", name)), key)?;
+    Ok(KeyChange { language: T::NAME, display_name: T::DISPLAY_NAME, keys: vec![name] })
+}
+
+pub fn remove<T: Toolchain>(name: &str) -> Result<KeyChange> {
+    check_key_name::<T>(name)?;
+    let path = keyring_dir::<T>()?.join(format!("{}.gpg", name));
+    if !path.exists() {
+        bail!(Error::new(ErrorKind::NotFound, format!("No trusted {} key named '{}'.", T::DISPLAY_NAME, name))
+            .hint("Run `mlvm keys list` to see the trusted keys."));
+    }
+    fs::remove_file(path)?;
+    Ok(KeyChange { language: T::NAME, display_name: T::DISPLAY_NAME, keys: vec![name.to_string()] })
+}
+
+/// Key names become file names in the keyring, so they must be plain names.
+fn check_key_name<T: Toolchain>(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.@+".contains(c));
+    if !valid {
+        bail!(Error::new(ErrorKind::InvalidInput, format!("'{}' is not a valid {} key name.", name, T::DISPLAY_NAME))
+            .hint("Key names may only contain letters, digits and `-_.@+`. Rename the key file before importing it."));
+    }
+    Ok(())
+}
+
+/// Downloads a checksum listing and its signature from the artifact's release directory,
+/// checks the signature against the language's keyring and returns the hash for
+/// `filename` from the now trusted listing. The signature may be detached or, if the
+/// upstream clearsigns the listing, contain the listing itself.
+pub async fn fetch_signed_sibling_list<T: Toolchain>(
+    artifact_url: &str,
+    list_name: &str,
+    signature_name: &str,
+    filename: &str,
+) -> Result<String> {
+    let list_url = verify::sibling_url(artifact_url, list_name);
+    let signature_url = verify::sibling_url(artifact_url, signature_name);
+
+    if keyring_files::<T>()?.is_empty() {
+        if T::RELEASE_KEYS.is_empty() {
+            // Nothing shipped and nothing imported: the hash still protects against corruption.
+            status!(
+                "WARNING: no trusted {} keys, skipping the signature check. \
+                Import the release key with `mlvm keys import {} <file|url>`.",
+                T::DISPLAY_NAME,
+                T::NAME
+            );
+            return verify::fetch_from_sibling_list::<T>(artifact_url, list_name, filename).await;
+        }
+        update::<T>().await?;
+    }
+
+    let signature = download::<T>(&signature_url).await?;
+    let clearsigned = signature.starts_with(b"-----BEGIN PGP SIGNED MESSAGE-----");
+    let list = if clearsigned { None } else { Some(download::<T>(&list_url).await?) };
+
+    let check = || -> Result<Verdict> {
+        check_signature(&signature, list.as_deref(), &load_keys::<T>()?)
+            .with_context(|| format!("Could not verify the signature of {}", list_url))
+    };
+    let mut verdict = check()?;
+    if matches!(verdict, Verdict::UnknownKey(_)) && missing_release_keys::<T>()? {
+        // A release key added to mlvm after the keyring was filled.
+        update::<T>().await?;
+        verdict = check()?;
+    }
+    let (signer, signed) = match verdict {
+        Verdict::Good { signer, signed } => (signer, signed),
+        Verdict::UnknownKey(issuer) => bail!(Error::new(
+            ErrorKind::Signature,
+            format!("The signature was made by key {}, which is not a trusted {} key.", issuer, T::DISPLAY_NAME)
+        )
+        .hint(format!(
+            "If it is a new release key, run `mlvm keys update {}` or `mlvm keys import {} <file|url>`.",
+            T::NAME,
+            T::NAME
+        ))),
+    };
+    status!("Signature of {} verified ({}).", list_name, signer);
+
+    let signed = String::from_utf8_lossy(&signed);
+    verify::find_in_checksum_list(&signed, filename)
+        .with_context(|| verify::no_entry(&list_url, filename))
+}
+
+async fn download<T: Toolchain>(url: &str) -> Result<Vec<u8>> {
+    Ok(mirror::get::<T>(url)
+        .await?
+        .error_for_status()
+        .with_context(|| format!("Failed to download {}", url))?
+        .bytes()
+        .await?
+        .to_vec())
+}
+
+/// Whether a built-in release key is not in the keyring (yet).
+fn missing_release_keys<T: Toolchain>() -> Result<bool> {
+    let dir = keyring_dir::<T>()?;
+    Ok(T::RELEASE_KEYS.iter().any(|fpr| !dir.join(format!("{}.gpg", fpr)).exists()))
+}
+
+/// Every key in the language's keyring.
+fn load_keys<T: Toolchain>() -> Result<Vec<SignedPublicKey>> {
+    let mut keys = Vec::new();
+    for file in keyring_files::<T>()? {
+        let bytes = fs::read(&file)?;
+        keys.extend(parse_keys(&bytes).with_context(|| format!("Invalid key file {}", file.display()))?);
+    }
+    Ok(keys)
+}
+
+const ARMOR_BEGIN: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----";
+
+/// Parses ASCII-armored or binary OpenPGP public keys.
+fn parse_keys(input: &[u8]) -> Result<Vec<SignedPublicKey>> {
+    let invalid = |e: pgp::errors::Error| Error::new(ErrorKind::InvalidInput, format!("Invalid OpenPGP public key: {}", e));
+
+    // Binary OpenPGP packets always have the high bit of the first byte set.
+    let binary = input.first().is_some_and(|b| b & 0x80 != 0);
+    let text = String::from_utf8_lossy(input);
+    // An armored file may hold several blocks, which the parser reads one at a time.
+    let blocks: Vec<&[u8]> = if binary {
+        vec![input]
+    } else {
+        text.match_indices(ARMOR_BEGIN).map(|(start, _)| text[start..].as_bytes()).collect()
+    };
+
+    let mut keys = Vec::new();
+    for block in blocks {
+        let (parsed, _) = SignedPublicKey::from_reader_many(block).map_err(invalid)?;
+        for key in parsed {
+            keys.push(key.map_err(invalid)?);
+        }
+    }
+    if keys.is_empty() {
+        bail!(Error::new(ErrorKind::InvalidInput, "No OpenPGP public key found"));
+    }
+    Ok(keys)
+}
+
+fn key_fingerprint(key: &SignedPublicKey) -> String {
+    format!("{:X}", key.primary_key.fingerprint())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// The outcome of a signature check that did not fail outright.
+#[derive(Debug)]
+enum Verdict {
+    /// A trusted key made the signature. `signed` is the signed data.
+    Good { signer: String, signed: Vec<u8> },
+    /// No trusted key made the signature; the issuer's fingerprint or key id.
+    UnknownKey(String),
+}
+
+/// Checks a signature, either detached (with `data`) or clearsigned (`data` is `None`),
+/// against `keys`. A signature by a trusted key that does not verify is an error.
+fn check_signature(signature: &[u8], data: Option<&[u8]>, keys: &[SignedPublicKey]) -> Result<Verdict> {
+    let (signatures, signed) = match data {
+        Some(data) => {
+            let (detached, _) = DetachedSignature::from_reader_single(signature)
+                .map_err(|e| anyhow::anyhow!("Invalid signature file: {}", e))?;
+            (vec![detached.signature], data.to_vec())
+        }
+        None => {
+            let text = std::str::from_utf8(signature).context("The clearsigned file is not text")?;
+            let (message, _) = CleartextSignedMessage::from_string(text)
+                .map_err(|e| anyhow::anyhow!("Invalid clearsigned file: {}", e))?;
+            // The signature covers the text with CRLF line endings.
+            (message.signatures().to_vec(), message.signed_text().into_bytes())
+        }
+    };
+
+    let mut good = None;
+    let mut unknown = None;
+    for signature in &signatures {
+        let issuer = issuer(signature);
+        match find_signer(keys, signature) {
+            Some(key) => {
+                let signer = describe(key);
+                if let Err(problem) = check_signer(key, signature, &signed) {
+                    bail!(Error::new(ErrorKind::Signature, format!("{} Key: {}.", problem, signer)));
+                }
+                good.get_or_insert(signer);
+            }
+            None => unknown = Some(issuer),
+        }
+    }
+    Ok(match (good, unknown) {
+        (Some(signer), _) => Verdict::Good { signer, signed },
+        (None, Some(issuer)) => Verdict::UnknownKey(issuer),
+        (None, None) => bail!(Error::new(ErrorKind::Signature, "The signature file contains no signature.")),
+    })
+}
+
+/// The issuer of a signature: its fingerprint, or the key id on older signatures.
+fn issuer(signature: &Signature) -> String {
+    match signature.issuer_fingerprint().first() {
+        Some(fingerprint) => format!("{:X}", fingerprint),
+        None => signature.issuer_key_id().first().map(|id| hex(id.as_ref())).unwrap_or_else(|| "(unknown)".to_string()),
+    }
+}
+
+/// The trusted key whose primary key or one of its subkeys made `signature`.
+fn find_signer<'a>(keys: &'a [SignedPublicKey], signature: &Signature) -> Option<&'a SignedPublicKey> {
+    let fingerprints = signature.issuer_fingerprint();
+    let key_ids = signature.issuer_key_id();
+    let made_by = |key: &dyn KeyDetails| {
+        let fingerprint = key.fingerprint();
+        let key_id = key.legacy_key_id();
+        fingerprints.iter().any(|f| **f == fingerprint) || key_ids.iter().any(|id| **id == key_id)
+    };
+    keys.iter().find(|key| {
+        made_by(&key.primary_key) || key.public_subkeys.iter().any(|sub| made_by(&sub.key))
+    })
+}
+
+/// Verifies `signature` with the primary key or the subkey of `key` that made it. The key
+/// must not be revoked and must not have expired before the signature was made; a subkey
+/// also needs a binding by the primary key that allows signing. The error says what is wrong.
+fn check_signer(key: &SignedPublicKey, signature: &Signature, data: &[u8]) -> Result<(), &'static str> {
+    let primary = &key.primary_key;
+    if key.details.revocation_signatures.iter().any(|revocation| revocation.verify_key(primary).is_ok()) {
+        return Err("Signature from a revoked key.");
+    }
+    let made = signature.created().map_or(0, |t| t.as_secs());
+    if primary_expiry(key).is_some_and(|expiry| made >= expiry) {
+        return Err("Signature from an expired key.");
+    }
+    if signature.verify(primary, data).is_ok() {
+        return Ok(());
+    }
+
+    for sub in &key.public_subkeys {
+        if signature.verify(&sub.key, data).is_err() {
+            continue;
+        }
+        let valid = |sig: &&Signature| sig.verify_subkey_binding(primary, &sub.key).is_ok();
+        if sub.signatures.iter().filter(valid).any(|sig| sig.typ() == Some(SignatureType::SubkeyRevocation)) {
+            return Err("Signature from a revoked subkey.");
+        }
+        let binding = newest(sub.signatures.iter().filter(valid).filter(|sig| sig.typ() == Some(SignatureType::SubkeyBinding)));
+        let Some(binding) = binding else {
+            // Not bound by the primary key, so the subkey is not part of the trusted key.
+            continue;
+        };
+        // A signing subkey must also sign the primary key back, or anyone could claim it.
+        let backed = binding
+            .embedded_signature()
+            .is_some_and(|back| back.verify_primary_key_binding(&sub.key, primary).is_ok());
+        if !binding.key_flags().sign() || !backed {
+            return Err("Signature from a subkey that is not allowed to sign.");
+        }
+        if expiry(sub.key.created_at(), binding).is_some_and(|expiry| made >= expiry) {
+            return Err("Signature from an expired subkey.");
+        }
+        return Ok(());
+    }
+    Err("BAD signature. The checksum file may have been tampered with.")
+}
+
+/// When the primary key of `key` expires (seconds since the epoch), as its newest self-signature says.
+fn primary_expiry(key: &SignedPublicKey) -> Option<u32> {
+    let primary = &key.primary_key;
+    let certifications = key.details.users.iter().flat_map(|user| {
+        user.signatures
+            .iter()
+            .filter(|sig| sig.verify_certification(primary, Tag::UserId, &user.id).is_ok())
+    });
+    let direct = key.details.direct_signatures.iter().filter(|sig| sig.verify_key(primary).is_ok());
+    expiry(primary.created_at(), newest(certifications.chain(direct))?)
+}
+
+/// When a key created at `created` expires under `self_signature`, if it does.
+fn expiry(created: Timestamp, self_signature: &Signature) -> Option<u32> {
+    let lifetime = self_signature.key_expiration_time()?.as_secs();
+    (lifetime != 0).then(|| created.as_secs().saturating_add(lifetime))
+}
+
+fn newest<'a>(signatures: impl Iterator<Item = &'a Signature>) -> Option<&'a Signature> {
+    signatures.max_by_key(|sig| sig.created().map_or(0, |t| t.as_secs()))
+}
+
+/// `Name <email> FINGERPRINT`, as shown after a good signature.
+fn describe(key: &SignedPublicKey) -> String {
+    let uid = key.details.users.first().map(|user| String::from_utf8_lossy(user.id.id()).to_string());
+    match uid {
+        Some(uid) => format!("{} {}", uid, key_fingerprint(key)),
+        None => key_fingerprint(key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::error;
+
+    const RELEASE_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/release.asc");
+    const OTHER_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/other.asc");
+    const SHASUMS: &[u8] = include_bytes!("../../tests/fixtures/keys/SHASUMS256.txt");
+    const DETACHED: &[u8] = include_bytes!("../../tests/fixtures/keys/SHASUMS256.txt.sig");
+    const CLEARSIGNED: &[u8] = include_bytes!("../../tests/fixtures/keys/SHASUMS256.txt.asc");
+    const BY_OTHER_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/other.sig");
+    const RELEASE_FINGERPRINT: &str = "E52E28700A16A054D494D0CF40AB600BA7F9D276";
+    const REVOKED_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/revoked.asc");
+    const BY_REVOKED_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/revoked.sig");
+    /// Valid from 2020-01-01 to 2021-06-01; `expiring.sig` was made in 2020, `expired.sig` in 2022.
+    const EXPIRING_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/expiring.asc");
+    const BEFORE_EXPIRY: &[u8] = include_bytes!("../../tests/fixtures/keys/expiring.sig");
+    const AFTER_EXPIRY: &[u8] = include_bytes!("../../tests/fixtures/keys/expired.sig");
+    /// A certify-only primary key with a signing subkey, which made `subkey.sig`. In
+    /// `encrypt-only.asc` a later binding turns the subkey into an encryption key.
+    const SUBKEY_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/subkey.asc");
+    const ENCRYPT_ONLY_KEY: &[u8] = include_bytes!("../../tests/fixtures/keys/encrypt-only.asc");
+    const BY_SUBKEY: &[u8] = include_bytes!("../../tests/fixtures/keys/subkey.sig");
+
+    fn release_keys() -> Vec<SignedPublicKey> {
+        parse_keys(RELEASE_KEY).unwrap()
+    }
+
+    #[test]
+    fn parses_armored_and_binary_keys() {
+        let keys = release_keys();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(key_fingerprint(&keys[0]), RELEASE_FINGERPRINT);
+        // Keys are stored in binary form and must read back the same.
+        let binary = keys[0].to_bytes().unwrap();
+        assert_eq!(key_fingerprint(&parse_keys(&binary).unwrap()[0]), RELEASE_FINGERPRINT);
+        // Several keys in one file.
+        let both = [RELEASE_KEY, OTHER_KEY].concat();
+        assert_eq!(parse_keys(&both).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rejects_input_without_keys() {
+        for input in [&b""[..], b"not a key", b"-----BEGIN PGP PUBLIC KEY BLOCK-----\n\n!!!\n-----END PGP PUBLIC KEY BLOCK-----\n"] {
+            let err = parse_keys(input).unwrap_err();
+            assert_eq!(error::kind(&err), ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn accepts_a_detached_signature() {
+        let verdict = check_signature(DETACHED, Some(SHASUMS), &release_keys()).unwrap();
+        let Verdict::Good { signer, signed } = verdict else { panic!("{verdict:?}") };
+        assert!(signer.contains(RELEASE_FINGERPRINT), "{signer}");
+        assert_eq!(signed, SHASUMS);
+    }
+
+    #[test]
+    fn accepts_a_clearsigned_listing() {
+        let verdict = check_signature(CLEARSIGNED, None, &release_keys()).unwrap();
+        let Verdict::Good { signed, .. } = verdict else { panic!("{verdict:?}") };
+        let signed = String::from_utf8(signed).unwrap();
+        assert_eq!(
+            verify::find_in_checksum_list(&signed, "node-v20.11.0-linux-x64.tar.xz").as_deref(),
+            Some("9a8b7c6d5e4f3a2b")
+        );
+    }
+
+    #[test]
+    fn rejects_a_tampered_listing() {
+        let tampered = String::from_utf8_lossy(SHASUMS).replace("9a8b", "0000");
+        let err = check_signature(DETACHED, Some(tampered.as_bytes()), &release_keys()).unwrap_err();
+        assert_eq!(error::kind(&err), ErrorKind::Signature);
+
+        let tampered = String::from_utf8_lossy(CLEARSIGNED).replace("9a8b", "0000");
+        let err = check_signature(tampered.as_bytes(), None, &release_keys()).unwrap_err();
+        assert_eq!(error::kind(&err), ErrorKind::Signature);
+    }
+
+    #[test]
+    fn reports_signatures_by_untrusted_keys() {
+        let verdict = check_signature(BY_OTHER_KEY, Some(SHASUMS), &release_keys()).unwrap();
+        let other = key_fingerprint(&parse_keys(OTHER_KEY).unwrap()[0]);
+        assert!(matches!(verdict, Verdict::UnknownKey(issuer) if issuer == other));
+        assert!(matches!(check_signature(DETACHED, Some(SHASUMS), &[]).unwrap(), Verdict::UnknownKey(_)));
+    }
+
+    fn signature_error(signature: &[u8], key: &[u8]) -> String {
+        let err = check_signature(signature, Some(SHASUMS), &parse_keys(key).unwrap()).unwrap_err();
+        assert_eq!(error::kind(&err), ErrorKind::Signature);
+        err.to_string()
+    }
+
+    #[test]
+    fn rejects_signatures_by_revoked_keys() {
+        assert!(signature_error(BY_REVOKED_KEY, REVOKED_KEY).contains("revoked key"));
+
+        // A revocation only counts if the key itself made it.
+        let mut keys = release_keys();
+        keys[0].details.revocation_signatures = parse_keys(REVOKED_KEY).unwrap()[0].details.revocation_signatures.clone();
+        assert!(!keys[0].details.revocation_signatures.is_empty());
+        assert!(matches!(check_signature(DETACHED, Some(SHASUMS), &keys).unwrap(), Verdict::Good { .. }));
+    }
+
+    #[test]
+    fn rejects_signatures_made_after_the_key_expired() {
+        let keys = parse_keys(EXPIRING_KEY).unwrap();
+        assert!(matches!(check_signature(BEFORE_EXPIRY, Some(SHASUMS), &keys).unwrap(), Verdict::Good { .. }));
+        assert!(signature_error(AFTER_EXPIRY, EXPIRING_KEY).contains("expired key"));
+    }
+
+    #[test]
+    fn subkeys_must_be_bound_as_signing_keys() {
+        let keys = parse_keys(SUBKEY_KEY).unwrap();
+        assert!(matches!(check_signature(BY_SUBKEY, Some(SHASUMS), &keys).unwrap(), Verdict::Good { .. }));
+        assert!(signature_error(BY_SUBKEY, ENCRYPT_ONLY_KEY).contains("not allowed to sign"));
+    }
+
+    #[test]
+    fn finds_release_keys_in_a_bundle() {
+        let note = b"The node release keys built into mlvm. Generated by keys/update.sh; do not edit.\n\n";
+        assert!(parse_bundle(note, &[]).unwrap().is_empty());
+        let err = parse_bundle(note, &[RELEASE_FINGERPRINT]).unwrap_err();
+        assert_eq!(error::kind(&err), ErrorKind::Signature);
+
+        let bundle = [&note[..], RELEASE_KEY, OTHER_KEY].concat();
+        let keys = parse_bundle(&bundle, &[RELEASE_FINGERPRINT]).unwrap();
+        let found = find_key(&keys, &RELEASE_FINGERPRINT.to_lowercase()).unwrap();
+        assert_eq!(key_fingerprint(found), RELEASE_FINGERPRINT);
+        assert!(find_key(&keys, "F3DCC08A8572C0749B3E18888EAB4D40A7B22B59").is_none());
+    }
+
+    #[test]
+    fn every_release_key_is_built_in() {
+        fn check<T: Toolchain>() {
+            let keys = bundled_keys::<T>().unwrap();
+            for fingerprint in T::RELEASE_KEYS {
+                assert!(find_key(&keys, fingerprint).is_some(), "{} does not ship {}", T::NAME, fingerprint);
+            }
+            for key in &keys {
+                let fingerprint = key_fingerprint(key);
+                assert!(T::RELEASE_KEYS.contains(&fingerprint.as_str()), "{} ships {}", T::NAME, fingerprint);
+            }
+        }
+        check::<Node>();
+        check::<Bun>();
+    }
+
+    #[test]
+    fn key_names_stay_inside_the_keyring() {
+        use crate::commands::node::Node;
+        assert!(check_key_name::<Node>(RELEASE_FINGERPRINT).is_ok());
+        assert!(check_key_name::<Node>("bun-key.2024").is_ok());
+        for bad in ["", ".", "..", "../x", "a/b", "a\\b", ".hidden", "a b"] {
+            assert!(check_key_name::<Node>(bad).is_err(), "{bad}");
+        }
+    }
+}
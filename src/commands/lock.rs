@@ -0,0 +1,178 @@
+// src/commands/lock.rs
+//
+// Advisory file locks that keep parallel mlvm processes (e.g. CI jobs sharing a
+// host) from stepping on each other. Locks live in `~/.mlvm/<lang>/.locks`:
+// `language.lock` guards shared state (`current`, metadata, uninstalls), and
+// `version-<version>.lock` is held for a whole install of that version. Always take a
+// version lock before the language lock. `staging.lock` is held for a moment
+// while a staging directory is set up, or judged abandoned and removed.
+// `~/.mlvm/cache/.locks/<key>.lock` is held by an install while it uses that
+// cache entry; cleaning the cache skips entries that are locked.
+//
+// The OS drops a lock when its process exits, so a crashed run never leaves
+// one behind. Lock files are never deleted; that would race with waiters.
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::commands::error::{Error, ErrorKind};
+use crate::commands::output::status;
+
+pub const LOCK_DIR: &str = ".locks";
+const LANGUAGE_LOCK: &str = "language";
+const STAGING_LOCK: &str = "staging";
+/// Keeps version locks apart from the fixed ones, whatever the version is called.
+const VERSION_LOCK_PREFIX: &str = "version-";
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+static TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// Called once from `main` with the value of `--lock-timeout`.
+pub fn set_timeout(timeout: Duration) {
+    TIMEOUT.set(timeout).ok();
+}
+
+/// Held until dropped.
+#[derive(Debug)]
+pub struct Lock {
+    _file: fs::File,
+}
+
+/// Locks everything that is shared between the versions of a language.
+pub fn language(lang_dir: &Path) -> Result<Lock> {
+    acquire(lang_dir, LANGUAGE_LOCK, "this language")
+}
+
+/// Locks one version of a language, e.g. for the duration of its install.
+pub fn version(lang_dir: &Path, version: &str) -> Result<Lock> {
+    acquire(lang_dir, &version_lock_name(version), version)
+}
+
+fn version_lock_name(version: &str) -> String {
+    format!("{}{}", VERSION_LOCK_PREFIX, version)
+}
+
+/// Locks the staging directories of a language (see `commands::staging`). Never held
+/// while waiting for anything else.
+pub fn staging(lang_dir: &Path) -> Result<Lock> {
+    acquire(lang_dir, STAGING_LOCK, "the staging directories")
+}
+
+/// Locks the cache entry `key` (its directory in `cache_dir`) while an install reads or writes it.
+pub fn cache_entry(cache_dir: &Path, key: &str) -> Result<Lock> {
+    acquire(cache_dir, key, "a cached download")
+}
+
+/// `cache_entry`, or `None` right away if another process holds it.
+pub fn try_cache_entry(cache_dir: &Path, key: &str) -> Option<Lock> {
+    acquire_within(cache_dir, key, "a cached download", Duration::ZERO).ok()
+}
+
+/// Locks the shim directory while it is rebuilt.
+pub fn shims(shims_dir: &Path) -> Result<Lock> {
+    acquire(shims_dir, "shims", "the shims")
+}
+
+fn acquire(lang_dir: &Path, name: &str, what: &str) -> Result<Lock> {
+    acquire_within(lang_dir, name, what, *TIMEOUT.get().unwrap_or(&DEFAULT_TIMEOUT))
+}
+
+fn acquire_within(lang_dir: &Path, name: &str, what: &str, timeout: Duration) -> Result<Lock> {
+    let dir = lang_dir.join(LOCK_DIR);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.lock", name));
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+
+    let started = Instant::now();
+    let mut announced = false;
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(Lock { _file: file }),
+            Err(fs::TryLockError::WouldBlock) => {}
+            Err(fs::TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+            }
+        }
+        if started.elapsed() >= timeout {
+            bail!(Error::new(
+                ErrorKind::Locked,
+                format!(
+                    "Timed out after {} waiting for another mlvm process working on {}.",
+                    humantime::format_duration(timeout),
+                    what
+                )
+            )
+            .hint("Raise the limit with --lock-timeout or MLVM_LOCK_TIMEOUT."));
+        }
+        if !announced {
+            status!("Waiting for another mlvm process working on {}...", what);
+            announced = true;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::error;
+
+    const SHORT: Duration = Duration::from_millis(300);
+
+    #[test]
+    fn a_held_lock_times_out_other_takers() {
+        let dir = tempfile::tempdir().unwrap();
+        let held = language(dir.path()).unwrap();
+
+        let started = Instant::now();
+        let err = acquire_within(dir.path(), LANGUAGE_LOCK, "this language", SHORT).unwrap_err();
+        assert!(started.elapsed() >= SHORT);
+        assert_eq!(error::kind(&err), ErrorKind::Locked);
+        assert!(err.to_string().contains("this language"), "{err}");
+
+        drop(held);
+        acquire_within(dir.path(), LANGUAGE_LOCK, "this language", SHORT).unwrap();
+    }
+
+    #[test]
+    fn waiters_get_the_lock_once_it_is_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let held = version(dir.path(), "v20.11.0").unwrap();
+        let releaser = std::thread::spawn(move || {
+            std::thread::sleep(SHORT);
+            drop(held);
+        });
+        let started = Instant::now();
+        acquire_within(dir.path(), &version_lock_name("v20.11.0"), "v20.11.0", Duration::from_secs(30)).unwrap();
+        assert!(started.elapsed() >= SHORT);
+        releaser.join().unwrap();
+    }
+
+    #[test]
+    fn locks_of_different_names_do_not_contend() {
+        let dir = tempfile::tempdir().unwrap();
+        let _language = language(dir.path()).unwrap();
+        let _node20 = version(dir.path(), "v20.11.0").unwrap();
+        let _node22 = version(dir.path(), "v22.1.0").unwrap();
+        assert!(acquire_within(dir.path(), &version_lock_name("v22.1.0"), "v22.1.0", SHORT).is_err());
+    }
+
+    #[test]
+    fn versions_never_share_a_lock_with_the_fixed_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let _language = language(dir.path()).unwrap();
+        let _staging = staging(dir.path()).unwrap();
+        // Version names are only checked to be single directories, so these are possible.
+        for odd in [LANGUAGE_LOCK, STAGING_LOCK] {
+            acquire_within(dir.path(), &version_lock_name(odd), odd, SHORT).unwrap();
+        }
+    }
+}
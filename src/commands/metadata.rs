@@ -0,0 +1,62 @@
+// src/commands/metadata.rs
+//
+// A small JSON record written next to every installed version, so `list` can
+// tell where a version came from and when it was installed.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Hidden directory inside `~/.mlvm/<lang>` holding one `<version>.json` per install.
+pub const METADATA_DIR: &str = ".meta";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstallMetadata {
+    pub version: String,
+    pub source_url: String,
+    /// LTS codename of the release, so `use lts/*` works offline.
+    #[serde(default)]
+    pub lts: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub installed_at: u64,
+}
+
+impl InstallMetadata {
+    pub fn new(version: &str, source_url: &str, lts: Option<String>) -> Self {
+        let installed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        InstallMetadata {
+            version: version.to_string(),
+            source_url: source_url.to_string(),
+            lts,
+            installed_at,
+        }
+    }
+}
+
+fn path(lang_dir: &Path, version: &str) -> PathBuf {
+    lang_dir.join(METADATA_DIR).join(format!("{}.json", version))
+}
+
+pub fn write(lang_dir: &Path, meta: &InstallMetadata) -> Result<()> {
+    let path = path(lang_dir, &meta.version);
+    fs::create_dir_all(lang_dir.join(METADATA_DIR))?;
+    fs::write(path, serde_json::to_vec_pretty(meta)?)?;
+    Ok(())
+}
+
+/// Returns `None` for versions installed before metadata existed or when the file is unreadable.
+pub fn read(lang_dir: &Path, version: &str) -> Option<InstallMetadata> {
+    let bytes = fs::read(path(lang_dir, version)).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+pub fn remove(lang_dir: &Path, version: &str) -> Result<()> {
+    match fs::remove_file(path(lang_dir, version)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
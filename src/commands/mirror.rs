@@ -0,0 +1,194 @@
+// src/commands/mirror.rs
+//
+// Where each language's version index and archives are downloaded from.
+// Every toolchain names its upstream base URLs; a list of mirrors can replace
+// them, from (highest priority first):
+//
+// - `MLVM_<LANG>_MIRROR` / `MLVM_<LANG>_INDEX_MIRROR` (comma separated),
+// - `mirrors.<lang>.download` / `mirrors.<lang>.index` in the config file,
+// - the upstream itself.
+//
+// Mirrors are tried in order; the next one is used when a request fails.
+// When the index lives under the download base (Node.js, Go), the download
+// mirrors serve the index too unless index mirrors are set explicitly.
+use anyhow::Result;
+use reqwest::header::HeaderMap;
+use reqwest::{Response, StatusCode};
+use std::env;
+
+use crate::commands::config::{self, MirrorConfig};
+use crate::commands::http;
+use crate::commands::output::status;
+use crate::commands::toolchain::Toolchain;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endpoint {
+    /// The list of published versions.
+    Index,
+    /// Archives and the checksum files next to them.
+    Download,
+}
+
+/// The base URLs for `endpoint`, in the order they should be tried.
+pub fn bases<T: Toolchain>(endpoint: Endpoint) -> Result<Vec<String>> {
+    let config = config::load()?;
+    let env_var = |suffix: &str| env::var(format!("MLVM_{}_{}", T::NAME.to_ascii_uppercase(), suffix)).ok();
+    Ok(choose_bases::<T>(endpoint, env_var, config.mirrors.get(T::NAME)))
+}
+
+/// `bases`, given the `MLVM_<LANG>_<suffix>` variables and the language's config section.
+fn choose_bases<T: Toolchain>(
+    endpoint: Endpoint,
+    env_var: impl Fn(&str) -> Option<String>,
+    configured: Option<&MirrorConfig>,
+) -> Vec<String> {
+    let env_list = |suffix: &str| env_var(suffix).map(|v| split_list(&v)).filter(|l| !l.is_empty());
+    let download = || {
+        env_list("MIRROR")
+            .or_else(|| configured.and_then(|m| m.download.clone()).filter(|l| !l.is_empty()))
+            .unwrap_or_else(|| vec![T::DOWNLOAD_URL.to_string()])
+    };
+
+    let bases = match endpoint {
+        Endpoint::Download => download(),
+        Endpoint::Index => env_list("INDEX_MIRROR")
+            .or_else(|| configured.and_then(|m| m.index.clone()).filter(|l| !l.is_empty()))
+            .unwrap_or_else(|| {
+                if T::INDEX_URL == T::DOWNLOAD_URL {
+                    download()
+                } else {
+                    vec![T::INDEX_URL.to_string()]
+                }
+            }),
+    };
+    bases.into_iter().map(|b| b.trim_end_matches('/').to_string()).collect()
+}
+
+/// `path` on the first mirror of `endpoint`.
+pub fn url<T: Toolchain>(endpoint: Endpoint, path: &str) -> Result<String> {
+    let bases = bases::<T>(endpoint)?;
+    Ok(format!("{}{}", bases[0], path))
+}
+
+/// `url` followed by the same file on every other mirror of the endpoint it belongs to.
+pub fn candidates<T: Toolchain>(url: &str) -> Result<Vec<String>> {
+    for endpoint in [Endpoint::Download, Endpoint::Index] {
+        let bases = bases::<T>(endpoint)?;
+        if let Some(path) = bases.iter().find_map(|b| url.strip_prefix(b.as_str())) {
+            let mut urls = vec![url.to_string()];
+            urls.extend(
+                bases
+                    .iter()
+                    .map(|b| format!("{}{}", b, path))
+                    .filter(|u| u != url),
+            );
+            return Ok(urls);
+        }
+    }
+    Ok(vec![url.to_string()])
+}
+
+/// `GET url`, moving on to the next mirror when a mirror fails. The last mirror's
+/// response is returned as is, so callers still see its status.
+pub async fn get<T: Toolchain>(url: &str) -> Result<Response> {
+    get_with_headers::<T>(url, HeaderMap::new()).await
+}
+
+/// `get` with extra request headers. A `304 Not Modified` counts as success.
+pub async fn get_with_headers<T: Toolchain>(url: &str, headers: HeaderMap) -> Result<Response> {
+    first_answer(&candidates::<T>(url)?, headers).await
+}
+
+/// Requests `urls` in order until one answers with success or `304`.
+pub async fn first_answer(urls: &[String], headers: HeaderMap) -> Result<Response> {
+    let last = urls.len() - 1;
+    for (i, url) in urls.iter().enumerate() {
+        let result = http::get_with_headers(url, headers.clone()).await;
+        let failure = match &result {
+            Ok(response) if response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED => {
+                return result
+            }
+            Ok(response) => response.status().to_string(),
+            Err(e) => e.to_string(),
+        };
+        if i == last {
+            return result;
+        }
+        status!("{} failed ({}), trying the next mirror...", url, failure);
+    }
+    unreachable!("there is always at least one candidate")
+}
+
+/// `a, b,c` -> `[a, b, c]`
+pub fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::bun::Bun;
+    use crate::commands::node::Node;
+    use crate::commands::test_support::{self, Response};
+
+    fn mirrors(download: &[&str], index: &[&str]) -> MirrorConfig {
+        let list = |l: &[&str]| (!l.is_empty()).then(|| l.iter().map(|s| s.to_string()).collect());
+        MirrorConfig { download: list(download), index: list(index) }
+    }
+
+    #[test]
+    fn environment_beats_config_beats_upstream() {
+        let no_env = |_: &str| None;
+        assert_eq!(choose_bases::<Node>(Endpoint::Download, no_env, None), [Node::DOWNLOAD_URL]);
+
+        let config = mirrors(&["https://config.example/node/"], &[]);
+        assert_eq!(
+            choose_bases::<Node>(Endpoint::Download, no_env, Some(&config)),
+            ["https://config.example/node"]
+        );
+
+        let env = |suffix: &str| (suffix == "MIRROR").then(|| "https://a.example, https://b.example/,".to_string());
+        assert_eq!(
+            choose_bases::<Node>(Endpoint::Download, env, Some(&config)),
+            ["https://a.example", "https://b.example"]
+        );
+        // An empty variable does not count as a list of mirrors.
+        let empty = |_: &str| Some(" , ".to_string());
+        assert_eq!(
+            choose_bases::<Node>(Endpoint::Download, empty, Some(&config)),
+            ["https://config.example/node"]
+        );
+    }
+
+    #[test]
+    fn download_mirrors_serve_an_index_that_lives_below_the_downloads() {
+        let no_env = |_: &str| None;
+        let config = mirrors(&["https://mirror.example/node"], &[]);
+        // Node's index.json sits in the download tree; Bun's release list is on another host.
+        assert_eq!(choose_bases::<Node>(Endpoint::Index, no_env, Some(&config)), ["https://mirror.example/node"]);
+        assert_eq!(choose_bases::<Bun>(Endpoint::Index, no_env, Some(&config)), [Bun::INDEX_URL]);
+
+        let config = mirrors(&["https://mirror.example/node"], &["https://index.example"]);
+        assert_eq!(choose_bases::<Node>(Endpoint::Index, no_env, Some(&config)), ["https://index.example"]);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_next_mirror() {
+        let broken = test_support::serve(|_| Response::new(404, "Not Found"));
+        let working = test_support::serve(|request| Response::new(200, request.path.clone()));
+        let urls = [format!("{}/dist/index.json", broken), format!("{}/dist/index.json", working)];
+
+        let response = first_answer(&urls, HeaderMap::new()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "/dist/index.json");
+
+        // The last mirror's answer is returned as is.
+        let response = first_answer(&urls[..1], HeaderMap::new()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
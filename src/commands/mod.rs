@@ -1,4 +1,28 @@
-pub mod node; 
-pub mod python;  
+pub mod alias;
+pub mod cache;
+pub mod config;
+pub mod download;
+pub mod engine;
+pub mod error;
+pub mod extract;
+pub mod http;
+pub mod index;
+pub mod keys;
+pub mod lock;
+pub mod metadata;
+pub mod mirror;
+pub mod output;
+pub mod progress;
+pub mod resolve;
+pub mod shell;
+pub mod shims;
+pub mod staging;
+pub mod verify;
+pub mod toolchain;
+#[cfg(test)]
+pub mod test_support;
+
+pub mod node;
+pub mod python;
 pub mod go;
-pub mod bun;
\ No newline at end of file
+pub mod bun;
@@ -1,15 +1,15 @@
-use anyhow::{bail, Context, Result};
+// src/commands/node.rs
+use anyhow::{bail, Result};
 use serde::Deserialize;
-use std::fs;
-use flate2::read::GzDecoder; // For Gzip decompression
-use tar::Archive;           // For Tar archive handling
-use std::env; // <-- Import the `env` module to get OS info
-use zip::ZipArchive;
-#[cfg(windows)]
-use std::os::windows::fs as windows_fs;
+use std::env;
+use std::path::PathBuf;
+
+use super::keys;
+use super::error::unsupported_platform;
+use super::index;
+use super::mirror::{self, Endpoint};
+use super::toolchain::{Artifact, ArchiveKind, RemoteVersion, Toolchain};
 
-#[cfg(windows)]
-use std::io; // This was only used in the Windows specific error handling
 
 // A struct that represents the fields we care about in the JSON response.
 // `serde` will automatically map the JSON keys to these struct fields.
@@ -19,222 +19,124 @@ struct NodeVersion {
     lts: serde_json::Value, // Can be a string or `false`, so we use a generic Value
 }
 
-// This is our main async function for this command.
-pub async fn list_remote() -> Result<()> {
-    println!("Fetching available Node.js versions...");
-
-    // 1. Define the URL of the official Node.js versions JSON index.
-    const NODE_DIST_URL: &str = "https://nodejs.org/dist/index.json";
-
-    // 2. Make the HTTP GET request.
-    // The `?` operator will propagate any network errors.
-    let response = reqwest::get(NODE_DIST_URL).await?;
-
-    // 3. Parse the JSON response into a vector of our `NodeVersion` structs.
-    // The `json()` method is a convenience from `reqwest` (enabled by our "json" feature flag)
-    // that handles deserialization for us.
-    let versions: Vec<NodeVersion> = response.json().await?;
-
-    println!("Available versions:");
-    // 4. Iterate and print the versions.
-    for version in versions {
-        // Check if the `lts` field is a string to label it nicely.
-        if version.lts.is_string() {
-            println!("- {} (LTS: {})", version.version, version.lts.as_str().unwrap());
-        } else {
-            println!("- {}", version.version);
-        }
+pub struct Node;
+
+impl Node {
+    /// Node.js spells the host differently from Rust: `win`/`darwin` and `x64`.
+    fn target() -> Result<(&'static str, &'static str)> {
+        let os = match env::consts::OS {
+            "windows" => "win",
+            "macos" => "darwin",
+            "linux" => "linux",
+            other => bail!(unsupported_platform(format!("operating system {}", other))),
+        };
+        let arch = match env::consts::ARCH {
+            "x86_64" => "x64",
+            "aarch64" => "arm64", // For Apple Silicon Macs
+            other => bail!(unsupported_platform(format!("architecture {}", other))),
+        };
+        Ok((os, arch))
     }
-
-    Ok(())
 }
 
-pub async fn install(version: &str) -> Result<()> {
-    // 1. Sanitize the version string.
-    let version = if version.starts_with('v') {
-        version.to_string()
-    } else {
-        format!("v{}", version)
-    };
-    println!("Installing Node.js version {}...", version);
-
-    // 2. Determine the correct download URL based on OS and architecture.
-    // std::env::consts::OS will be "linux", "windows", "macos", etc.
-    let target_os = match env::consts::OS {
-        "windows" => "win",
-        "macos" => "darwin",
-        "linux" => "linux",
-        other => bail!("Unsupported operating system: {}", other),
-    };
-
-    // std::env::consts::ARCH will be "x86_64", "aarch64", etc.
-    let target_arch = match env::consts::ARCH {
-        "x86_64" => "x64",
-        "aarch64" => "arm64", // For Apple Silicon Macs
-        other => bail!("Unsupported architecture: {}", other),
-    };
-    
-    // The file extension is different for Windows.
-    let extension = if target_os == "win" { "zip" } else { "tar.gz" };
-
-    let filename = format!("node-{}-{}-{}.{}", version, target_os, target_arch, extension);
-    let download_url = format!("https://nodejs.org/dist/{}/{}", version, filename);
-
-    println!("Downloading from {}...", download_url);
-
-    // 3. Download the file.
-    let response = reqwest::get(&download_url).await?
-        .error_for_status()
-        .with_context(|| format!("Failed to download Node.js version {}. It might not exist for your platform.", version))?;
-
-    // We need the raw bytes for both zip and tar.
-    let file_bytes = response.bytes().await?;
-
-    // 4. Find the installation directory.
-    let mlvm_dir = dirs::home_dir()
-        .context("Could not find home directory")?
-        .join(".mlvm");
-    let lang_dir = mlvm_dir.join("node");
-    fs::create_dir_all(&lang_dir)?;
-
-    let install_path = lang_dir.join(&version);
-    if install_path.exists() {
-        println!("Version {} is already installed.", version);
-        return Ok(());
+impl Toolchain for Node {
+    const NAME: &'static str = "node";
+    const DISPLAY_NAME: &'static str = "Node.js";
+    // `.nvmrc` first, like nvm; both hold one spec such as `lts/hydrogen`, `v18` or `node`.
+    const VERSION_FILES: &'static [&'static str] = &[".nvmrc", ".node-version"];
+    const INDEX_URL: &'static str = "https://nodejs.org/dist";
+    const DOWNLOAD_URL: &'static str = "https://nodejs.org/dist";
+    // The release keys listed in https://github.com/nodejs/node#release-keys: the active
+    // releasers first, then the keys that signed earlier releases still on the mirror.
+    const RELEASE_KEYS: &'static [&'static str] = &[
+        "C0D6248439F1D5604AAFFB4021D900FFDB233756", // Antoine du Hamel
+        "DD792F5973C6DE52C432CBDAC77ABFA00DDBF2B7", // Juan José Arboleda
+        "CC68F5A3106FF448322E48ED27F5E38D5B0A215F", // Marco Ippolito
+        "8FCCA13FEF1D0C2E91008E09770F7A9A5AE15600", // Michaël Zasso
+        "890C08DB8579162FEE0DF9DB8BEAB4DFCF555EF4", // Rafael Gonzaga
+        "C82FA3AE1CBEDC6BE46B9360C43CEC45C17AB93C", // Richard Lau
+        "108F52B48DB57BB0CC439B2997B01419BD92F80A", // Ruy Adorno
+        "A363A499291CBBC940DD62E41F10027AF002F8B0", // Ulises Gascón
+        // Previous releasers.
+        "4ED778F539E3634C779C87C6D7062848A1AB005C", // Beth Griggs
+        "141F07595B7B3FFE74309A937405533BE57C7D57", // Bryan English
+        "9554F04D7259F04124DE6B476D5A82AC7E37093B", // Chris Dickinson
+        "94AE36675C464D64BAFA68DD7434390BDBE9B9C5", // Colin Ihrig
+        "1C050899334244A8AF75E53792EF661D867B9DFA", // Danielle Adams
+        "74F12602B6F1C4E913FAA37AD3A89613643B6201", // Danielle Adams
+        "B9AE9905FFD7803F25714661B63B535A4C206CA9", // Evan Lucas
+        "77984A986EBC2AA786BC0F66B01FBB92821C587A", // Gibson Fahnestock
+        "93C7E9E91B49E432C2F75674B0A78B0A6C481CF6", // Isaac Z. Schlueter
+        "56730D5401028683275BD23C23EFEFE93C4CFFFE", // Italo A. Casas
+        "71DCFD284A79C3B38668286BC97EC7A07EDE3FC1", // James M Snell
+        "FD3A5288F042B6850C66B31F09FE44734EB7990E", // Jeremiah Senkpiel
+        "61FC681DFB92A079F1685E77973F295594EC4689", // Juan José Arboleda
+        "114F43EE0176B71C7BC219DD50A3051F888C628D", // Julien Gilli
+        "C4F0DFFF4E8C1A8236409D08E73BC641CC11F4C8", // Myles Borins
+        "DD8F2338BAE7501E3DD5AC78C273792F7D83545D", // Rod Vagg
+        "A48C2BEE680E841632CD4E44F07496B3EB3C1762", // Ruben Bridgewater
+        "B9E2F5981AA6E0CD28160D9FF13993A75599653C", // Shelley Vohr
+        "7937DFD2AB06298B2293C3187D33FF9D0246406D", // Timothy J Fontaine
+    ];
+    const RELEASE_KEYRING: &'static [u8] = include_bytes!("../../keys/node.asc");
+
+    fn normalize_version(&self, version: &str) -> String {
+        if version.starts_with('v') {
+            version.to_string()
+        } else {
+            format!("v{}", version)
+        }
     }
-    println!("Unpacking to {:?}...", install_path);
 
-    // 5. Decompress and unpack based on the file extension.
-    let temp_unpack_path = lang_dir.join("temp_unpack");
-    if temp_unpack_path.exists() {
-        fs::remove_dir_all(&temp_unpack_path)?;
+    async fn list_remote(&self) -> Result<Vec<RemoteVersion>> {
+        let versions: Vec<NodeVersion> = index::json::<Self, _>("/index.json").await?;
+
+        Ok(versions
+            .into_iter()
+            .map(|v| RemoteVersion {
+                // `lts` is the codename for LTS releases and `false` otherwise.
+                lts: v.lts.as_str().map(str::to_string),
+                version: v.version,
+            })
+            .collect())
     }
 
-    if extension == "zip" {
-        // Use the `zip` crate to handle .zip files
-        let mut archive = ZipArchive::new(std::io::Cursor::new(file_bytes))?;
-        archive.extract(&temp_unpack_path)?;
-    } else {
-        // Use the `tar` and `flate2` crates for .tar.gz
-        let tar = GzDecoder::new(&file_bytes[..]);
-        let mut archive = Archive::new(tar);
-        archive.unpack(&temp_unpack_path)?;
+    async fn artifact(&self, version: &str) -> Result<Artifact> {
+        let (os, arch) = Self::target()?;
+        // The file extension is different for Windows.
+        let (kind, extension) = if os == "win" {
+            (ArchiveKind::Zip, "zip")
+        } else {
+            (ArchiveKind::TarGz, "tar.gz")
+        };
+
+        let root_dir = format!("node-{}-{}-{}", version, os, arch);
+        let filename = format!("{}.{}", root_dir, extension);
+        Ok(Artifact {
+            url: mirror::url::<Self>(Endpoint::Download, &format!("/{}/{}", version, filename))?,
+            filename,
+            kind,
+            root_dir,
+        })
     }
-    
-    // The unpacked folder name is also different for Windows.
-    let unpacked_folder_name = format!("node-{}-{}-{}", version, target_os, target_arch);
-    let source_path = temp_unpack_path.join(unpacked_folder_name);
-
-    fs::rename(source_path, &install_path)?;
-    fs::remove_dir_all(temp_unpack_path)?;
-
-    println!("Successfully installed Node.js {}", version);
 
-    Ok(())
-}
-
-pub fn use_version(version: &str) -> Result<()> {
-    // ... steps 1-4 (sanitizing version, finding paths) are the same ...
-    let version = if version.starts_with('v') {
-        version.to_string()
-    } else {
-        format!("v{}", version)
-    };
-    println!("Switching to Node.js version {}...", version);
-
-    let lang_dir = dirs::home_dir()
-        .context("Could not find home directory")?
-        .join(".mlvm")
-        .join("node");
-
-    let version_path = lang_dir.join(&version);
-    if !version_path.exists() {
-        bail!("Version {} is not installed. Please run `mlvm node install {}` first.", version, version.trim_start_matches('v'));
+    async fn sha256(&self, _version: &str, artifact: &Artifact) -> Result<String> {
+        // `SHASUMS256.txt.sig` is a detached signature by one of the release keys.
+        keys::fetch_signed_sibling_list::<Self>(
+            &artifact.url,
+            "SHASUMS256.txt",
+            "SHASUMS256.txt.sig",
+            &artifact.filename,
+        )
+        .await
     }
 
-    let current_symlink_path = lang_dir.join("current");
-
-    if current_symlink_path.exists() || current_symlink_path.symlink_metadata().is_ok() {
-         // On Windows, remove_file might fail for a directory symlink.
-         // A more robust approach would be to use remove_dir.
-        if current_symlink_path.is_dir() {
-            fs::remove_dir(&current_symlink_path).ok();
+    fn bin_dirs(&self) -> Vec<PathBuf> {
+        // Windows builds keep `node.exe` and `npm.cmd` at the top level.
+        if cfg!(windows) {
+            vec![PathBuf::new()]
         } else {
-            fs::remove_file(&current_symlink_path).ok();
-        }
-    }
-
-    // Create the new symlink.
-    let result = {
-        #[cfg(windows)]
-        {
-            windows_fs::symlink_dir(&version_path, &current_symlink_path)
-        }
-        #[cfg(unix)]
-        {
-            std::os::unix::fs::symlink(&version_path, &current_symlink_path)
-        }
-    };
-
-    // --- THIS IS THE NEW, INTELLIGENT ERROR HANDLING ---
-    if let Err(e) = result {
-        #[cfg(windows)]
-        {
-            // `ErrorKind::PermissionDenied` is often what os error 1314 maps to.
-            if e.kind() == io::ErrorKind::PermissionDenied {
-                bail!(
-                    "Failed to create symlink. This is a permissions issue.\n\n\
-                    On Windows, you must either:\n\
-                    1. (Recommended) Enable 'Developer Mode' in your Windows settings.\n\
-                       Go to Settings > Update & Security > For Developers > and turn on 'Developer Mode'.\n\
-                    2. Run this command in a terminal that is 'Run as Administrator'.\n\n\
-                    Original error: {}",
-                    e
-                );
-            }
+            vec![PathBuf::from("bin")]
         }
-        // For any other error, or on other platforms, just return it directly.
-        return Err(e).context("Failed to create symlink");
     }
-    #[cfg(unix)]
-    let bin_path = current_symlink_path.join("bin");
-    #[cfg(windows)]
-    let bin_path = current_symlink_path;
-
-    println!("  Add this to your PATH: {}", bin_path.display());
-    
-    println!("Successfully switched to Node.js {}", version);
-    println!("\nTo finish setup, you must add the 'current' directory to your PATH.");
-    println!("You can do this in PowerShell with:");
-    let current_bin_path = lang_dir.join("current");
-    println!("  $env:PATH = \"{};\" + $env:PATH", current_bin_path.to_string_lossy());
-    println!("\nTo make this permanent, add that line to your PowerShell profile.");
-    println!("Then, restart your terminal.");
-
-
-    Ok(())
 }
-
-pub fn list_local() -> Result<()> {
-    let lang_dir = dirs::home_dir()
-        .context("Could not find home directory")?
-        .join(".mlvm")
-        .join("node");
-
-    if !lang_dir.exists() {
-        println!("No Node.js versions installed yet.");
-        return Ok(());
-    }
-
-    println!("Installed Node.js versions:");
-    for entry in fs::read_dir(lang_dir)? {
-        let entry = entry?;
-        let file_name = entry.file_name().into_string().unwrap_or_default();
-        // We only list directories that start with 'v' to avoid listing 'current'.
-        if entry.file_type()?.is_dir() && file_name.starts_with('v') {
-            println!("- {}", file_name);
-        }
-    }
-
-    Ok(())
-}
\ No newline at end of file
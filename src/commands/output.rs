@@ -0,0 +1,117 @@
+// src/commands/output.rs
+//
+// What commands print. The default text format is prose for people. With
+// `--json` (`--format json`) every command prints exactly one JSON document
+// on stdout, errors included, and status messages ("Downloading...") move to
+// stderr so scripts can parse stdout as is. The documents are described in
+// the README; fields are only ever added, never renamed or removed.
+//
+// Used as a library, mlvm prints nothing: status messages and progress bars
+// only appear once `main` has picked a format.
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+use crate::cli::OutputFormat;
+use crate::commands::error::{self, ErrorKind};
+
+static JSON: AtomicBool = AtomicBool::new(false);
+static CLI: AtomicBool = AtomicBool::new(false);
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Called once from `main` with the value of `--format` / `--json`.
+pub fn set_format(format: OutputFormat) {
+    JSON.store(format == OutputFormat::Json, Ordering::Relaxed);
+    CLI.store(true, Ordering::Relaxed);
+}
+
+/// For commands whose stdout is code for the shell to run: status messages go to stderr.
+pub fn status_to_stderr() {
+    STATUS_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// Whether anything besides the result may be printed, i.e. mlvm runs as the CLI.
+pub fn is_cli() -> bool {
+    CLI.load(Ordering::Relaxed)
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Prints the result of a command: `value` as JSON, or whatever `text` prints.
+pub fn emit<T: Serialize>(value: &T, text: impl FnOnce(&T)) -> Result<()> {
+    if is_json() {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    } else {
+        text(value);
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ErrorDocument {
+    error: ErrorBody,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    message: String,
+    /// The underlying errors, outermost first.
+    causes: Vec<String>,
+    kind: ErrorKind,
+    exit_code: u8,
+    hint: Option<String>,
+}
+
+/// Reports a failed command: as `{"error": {"message": ..., "kind": ..., ...}}` with
+/// `--json`, else as the error, its causes and the hint on stderr.
+pub fn error(error: &anyhow::Error) {
+    let kind = error::kind(error);
+    let hint = error::hint(error);
+    if !is_json() {
+        eprintln!("Error: {:?}", error);
+        if let Some(hint) = hint {
+            eprintln!("\nHint: {}", hint);
+        }
+        return;
+    }
+    let document = ErrorDocument {
+        error: ErrorBody {
+            message: error.to_string(),
+            causes: error.chain().skip(1).map(|c| c.to_string()).collect(),
+            kind,
+            exit_code: kind.exit_code(),
+            hint,
+        },
+    };
+    println!("{}", serde_json::to_string_pretty(&document).unwrap_or_default());
+}
+
+/// Used by `status!`.
+pub fn status_line(args: fmt::Arguments) {
+    if !is_cli() {
+        return;
+    }
+    if is_json() || STATUS_TO_STDERR.load(Ordering::Relaxed) {
+        eprintln!("{}", args);
+    } else {
+        println!("{}", args);
+    }
+}
+
+/// `println!` for progress and status messages: stdout in the text format,
+/// stderr with `--json`, so they never mix with the JSON document.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        $crate::commands::output::status_line(format_args!($($arg)*))
+    };
+}
+pub(crate) use status;
+
+/// RFC 3339 in UTC, e.g. `2024-01-31T12:00:00Z`, for timestamps in JSON.
+pub fn timestamp(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()
+}
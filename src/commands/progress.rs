@@ -0,0 +1,80 @@
+// src/commands/progress.rs
+//
+// Progress reporting for long-running work. Bars are only drawn when stdout is
+// a terminal and neither `--quiet` nor `--json` was given, so CI logs stay clean.
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::commands::output;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Called once from `main` with the value of `--quiet`.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+fn enabled() -> bool {
+    shown(output::is_cli(), QUIET.load(Ordering::Relaxed), output::is_json(), std::io::stdout().is_terminal())
+}
+
+/// Bars are for a person watching the `mlvm` command in a terminal.
+fn shown(cli: bool, quiet: bool, json: bool, terminal: bool) -> bool {
+    cli && !quiet && !json && terminal
+}
+
+/// A byte counter with throughput and ETA. Without a known `total`
+/// (no Content-Length) it falls back to a spinner showing bytes and rate.
+pub fn download_bar(total: Option<u64>) -> ProgressBar {
+    if !enabled() {
+        return ProgressBar::hidden();
+    }
+    let bar = match total {
+        Some(total) => ProgressBar::new(total).with_style(
+            ProgressStyle::with_template(
+                "  [{bar:30.cyan/blue}] {bytes}/{total_bytes} {bytes_per_sec} ETA {eta}",
+            )
+            .expect("valid template")
+            .progress_chars("=> "),
+        ),
+        None => ProgressBar::new_spinner().with_style(
+            ProgressStyle::with_template("  {spinner} {bytes} {bytes_per_sec}").expect("valid template"),
+        ),
+    };
+    bar.enable_steady_tick(Duration::from_millis(120));
+    bar
+}
+
+/// A spinner for work without a measurable size, such as unpacking.
+pub fn spinner(message: &str) -> ProgressBar {
+    if !enabled() {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::new_spinner()
+        .with_style(ProgressStyle::with_template("  {spinner} {msg} {elapsed}").expect("valid template"));
+    bar.set_message(message.to_string());
+    bar.enable_steady_tick(Duration::from_millis(120));
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bars_only_show_in_an_interactive_terminal() {
+        assert!(shown(true, false, false, true));
+        // Piped into a CI log, `--quiet`, `--json`, or a library caller.
+        assert!(!shown(true, false, false, false));
+        assert!(!shown(true, true, false, true));
+        assert!(!shown(true, false, true, true));
+        assert!(!shown(false, false, false, true));
+
+        // Tests are library callers, so nothing is drawn.
+        assert!(download_bar(Some(1024)).is_hidden());
+        assert!(download_bar(None).is_hidden());
+        assert!(spinner("Unpacking...").is_hidden());
+    }
+}
@@ -1,11 +1,16 @@
 // src/commands/python.rs
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, Result};
 use serde::Deserialize;
 use std::env;
-use std::fs;
-use std::io::Cursor;
-use tar::Archive;
-use flate2::read::GzDecoder; // Ensure you have flate2 in Cargo.toml
+use std::path::PathBuf;
+
+use super::config;
+use super::error::{unsupported_platform, Error, ErrorKind};
+use super::index;
+use super::mirror::{self, Endpoint};
+use super::resolve::compare_versions;
+use super::verify;
+use super::toolchain::{Artifact, ArchiveKind, RemoteVersion, Toolchain};
 
 #[derive(Deserialize, Debug)]
 struct GitHubAsset {
@@ -18,189 +23,157 @@ struct GitHubRelease {
     assets: Vec<GitHubAsset>,
 }
 
-fn get_target_platform() -> Result<String> {
-    let arch = match env::consts::ARCH {
-        "x86_64" => "x86_64",
-        "aarch64" => "aarch64",
-        other => bail!("Unsupported architecture: {}", other),
-    };
-    let os = match env::consts::OS {
-        "linux" => "unknown-linux-gnu",
-        "macos" => "apple-darwin",
-        // The repo uses 'pc-windows-msvc' usually
-        "windows" => "pc-windows-msvc", 
-        other => bail!("Unsupported OS: {}", other),
-    };
-    Ok(format!("{}-{}", arch, os))
-}
-
-pub async fn list_remote() -> Result<()> {
-    println!("Fetching available Python versions...");
-    
-    let client = reqwest::Client::builder().user_agent("mlvm-rust").build()?;
-    let url = "https://api.github.com/repos/indygreg/python-build-standalone/releases/latest";
-    let response = client.get(url).send().await?.error_for_status()?;
-    let release: GitHubRelease = response.json().await?;
-
-    let platform = get_target_platform()?;
-    println!("Searching for builds compatible with: {}", platform);
-
-    let mut versions: Vec<String> = release.assets.iter()
-        .filter_map(|asset| {
-            // RELAXED FILTER: 
-            // 1. Must contain "cpython-"
-            // 2. Must contain platform string (e.g., x86_64-pc-windows-msvc)
-            // 3. Must be an archive we can handle (.tar.zst or .tar.gz)
-            // 4. Must be 'install_only' (smaller, no build tools)
-            if asset.name.starts_with("cpython-") 
-               && asset.name.contains(&platform)
-               && asset.name.contains("install_only")
-               && (asset.name.ends_with(".tar.zst") || asset.name.ends_with(".tar.gz"))
-            {
-                let parts: Vec<&str> = asset.name.split('-').collect();
-                // Format usually: cpython-<VER>+<DATE>-<ARCH>-...
-                if parts.len() > 1 {
-                    let ver_parts: Vec<&str> = parts[1].split('+').collect();
-                    return Some(ver_parts[0].to_string());
-                }
-            }
-            None
-        })
-        .collect();
-    
-    versions.sort();
-    versions.dedup();
-    versions.reverse(); 
-
-    if versions.is_empty() {
-        println!("No versions found. Please check if your platform is supported by python-build-standalone.");
-    } else {
-        println!("Available versions (Top 20):");
-        for v in versions.iter().take(20) {
-            println!("- {}", v);
-        }
+pub struct Python;
+
+impl Python {
+    fn target_platform() -> Result<String> {
+        let arch = match env::consts::ARCH {
+            "x86_64" => "x86_64",
+            "aarch64" => "aarch64",
+            other => bail!(unsupported_platform(format!("architecture {}", other))),
+        };
+        let os = match env::consts::OS {
+            "linux" => "unknown-linux-gnu",
+            "macos" => "apple-darwin",
+            // The repo uses 'pc-windows-msvc' usually
+            "windows" => "pc-windows-msvc",
+            other => bail!(unsupported_platform(format!("operating system {}", other))),
+        };
+        Ok(format!("{}-{}", arch, os))
     }
-    Ok(())
-}
 
-pub async fn install(version: &str) -> Result<()> {
-    println!("Fetching manifest to find Python {}...", version);
-    
-    let client = reqwest::Client::builder().user_agent("mlvm-rust").build()?;
-    let url = "https://api.github.com/repos/indygreg/python-build-standalone/releases/latest";
-    let response = client.get(url).send().await?.error_for_status()?;
-    let release: GitHubRelease = response.json().await?;
-
-    let target_platform = get_target_platform()?;
-    let search_prefix = format!("cpython-{}", version);
-    
-    let asset = release.assets.iter().find(|a| {
-        a.name.starts_with(&search_prefix) 
-        && a.name.contains(&target_platform) 
-        && a.name.contains("install_only")
-        && (a.name.ends_with(".tar.zst") || a.name.ends_with(".tar.gz"))
-    });
-
-    let asset = match asset {
-        Some(a) => a,
-        None => bail!("Could not find Python {} for {}.", version, target_platform),
-    };
-
-    println!("Downloading {}...", asset.name);
-    let response = reqwest::get(&asset.browser_download_url).await?.error_for_status()?;
-    let bytes = response.bytes().await?;
-
-    let mlvm_dir = dirs::home_dir().context("No home dir")?.join(".mlvm");
-    let lang_dir = mlvm_dir.join("python");
-    let install_path = lang_dir.join(version);
-    
-    if install_path.exists() {
-        println!("Version {} is already installed.", version);
-        return Ok(());
+    /// Downloads the latest release manifest and keeps only the assets we can install here:
+    /// builds of the configured flavor (`install_only` by default: smaller, no build tools)
+    /// for this platform in an archive format we handle.
+    async fn compatible_assets() -> Result<Vec<GitHubAsset>> {
+        let flavor = config::load()?.python.flavor.unwrap_or_default().asset_suffix();
+        let release: GitHubRelease = index::json::<Self, _>("/releases/latest").await?;
+
+        let platform = Self::target_platform()?;
+        Ok(release
+            .assets
+            .into_iter()
+            .filter(|a| {
+                a.name.starts_with("cpython-")
+                    && a.name.contains(&platform)
+                    && [".tar.zst", ".tar.gz"]
+                        .iter()
+                        .any(|ext| a.name.ends_with(&format!("-{}{}", flavor, ext)))
+            })
+            .collect())
     }
 
-    let temp_unpack_path = lang_dir.join("temp_unpack");
-    if temp_unpack_path.exists() { fs::remove_dir_all(&temp_unpack_path)?; }
-    
-    // Handle Decompression based on extension
-    if asset.name.ends_with(".tar.zst") {
-        println!("Unpacking .tar.zst...");
-        let decoder = zstd::stream::decode_all(Cursor::new(bytes))?;
-        let mut archive = Archive::new(Cursor::new(decoder));
-        archive.unpack(&temp_unpack_path)?;
-    } else if asset.name.ends_with(".tar.gz") {
-        println!("Unpacking .tar.gz...");
-        let decoder = GzDecoder::new(&bytes[..]);
-        let mut archive = Archive::new(decoder);
-        archive.unpack(&temp_unpack_path)?;
+    /// Asset names look like `cpython-<VER>+<DATE>-<ARCH>-...`.
+    fn asset_version(name: &str) -> Option<&str> {
+        let rest = name.strip_prefix("cpython-")?;
+        rest.split(['+', '-']).next()
     }
+}
 
-    let source_path = temp_unpack_path.join("python");
-    if !source_path.exists() {
-        bail!("Extracted archive did not contain 'python' folder.");
+impl Toolchain for Python {
+    const NAME: &'static str = "python";
+    const DISPLAY_NAME: &'static str = "Python";
+    const VERSION_FILES: &'static [&'static str] = &[".python-version"];
+    const INDEX_URL: &'static str = "https://api.github.com/repos/indygreg/python-build-standalone";
+    const DOWNLOAD_URL: &'static str =
+        "https://github.com/indygreg/python-build-standalone/releases/download";
+    const REMOTE_LIST_LIMIT: Option<usize> = Some(20);
+
+    fn normalize_version(&self, version: &str) -> String {
+        version.to_string()
     }
 
-    fs::create_dir_all(&lang_dir)?;
-    
-    // Retry rename logic (Windows file locking can sometimes fail instant renames)
-    let mut success = false;
-    for _ in 0..3 {
-        if fs::rename(&source_path, &install_path).is_ok() {
-            success = true;
-            break;
-        }
-        std::thread::sleep(std::time::Duration::from_millis(500));
-    }
-    if !success {
-        bail!("Failed to move extracted folder to installation path.");
+    async fn list_remote(&self) -> Result<Vec<RemoteVersion>> {
+        let assets = Self::compatible_assets().await?;
+        let mut versions: Vec<String> = assets
+            .iter()
+            .filter_map(|a| Self::asset_version(&a.name))
+            .map(str::to_string)
+            .collect();
+
+        versions.sort_by(|a, b| compare_versions(b, a));
+        versions.dedup();
+
+        Ok(versions
+            .into_iter()
+            .map(|version| RemoteVersion { version, lts: None })
+            .collect())
     }
 
-    fs::remove_dir_all(temp_unpack_path)?;
+    async fn artifact(&self, version: &str) -> Result<Artifact> {
+        let assets = Self::compatible_assets().await?;
+        // The `+` keeps `3.1` from matching `3.10.x`.
+        let search_prefix = format!("cpython-{}+", version);
+
+        let asset = match assets.into_iter().find(|a| a.name.starts_with(&search_prefix)) {
+            Some(a) => a,
+            None => bail!(Error::new(
+                ErrorKind::NotFound,
+                format!("Could not find Python {} for {}.", version, Self::target_platform()?)
+            )
+            .hint("Run `mlvm python list-remote` to see the versions built for this platform.")),
+        };
+
+        // The API reports upstream URLs (`.../releases/download/<tag>/<file>`);
+        // rebase them onto the download mirror.
+        let url = match asset.browser_download_url.split_once("/releases/download") {
+            Some((_, path)) => mirror::url::<Self>(Endpoint::Download, path)?,
+            None => asset.browser_download_url,
+        };
+        Ok(Artifact {
+            kind: ArchiveKind::from_filename(&asset.name).unwrap_or(ArchiveKind::TarGz),
+            url,
+            filename: asset.name,
+            root_dir: "python".to_string(),
+        })
+    }
 
-    println!("Successfully installed Python {}", version);
-    Ok(())
-}
+    async fn sha256(&self, _version: &str, artifact: &Artifact) -> Result<String> {
+        // Each release ships one `SHA256SUMS` covering every asset.
+        verify::fetch_from_sibling_list::<Self>(&artifact.url, "SHA256SUMS", &artifact.filename).await
+    }
 
-// Make sure this function is PUBLIC
-pub fn list_local() -> Result<()> {
-    let lang_dir = dirs::home_dir().context("No home")?.join(".mlvm").join("python");
-    if !lang_dir.exists() { 
-        println!("No Python versions installed.");
-        return Ok(()); 
+    /// pyenv syntax: any number of versions, whitespace- or line-separated, in priority
+    /// order. `system` (the interpreter outside pyenv) is left to PATH.
+    fn parse_version_file(&self, contents: &str) -> Vec<String> {
+        contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .flat_map(str::split_whitespace)
+            .filter(|spec| *spec != "system")
+            .map(str::to_string)
+            .collect()
     }
 
-    println!("Installed Python versions:");
-    for entry in fs::read_dir(lang_dir)? {
-        let entry = entry?;
-        let name = entry.file_name().to_string_lossy().to_string();
-        if entry.file_type()?.is_dir() && name != "current" && name != "temp_unpack" {
-            println!("- {}", name);
+    fn bin_dirs(&self) -> Vec<PathBuf> {
+        // Windows builds put `python.exe` at the top level and scripts in `Scripts`.
+        if cfg!(windows) {
+            vec![PathBuf::new(), PathBuf::from("Scripts")]
+        } else {
+            vec![PathBuf::from("bin")]
         }
     }
-    Ok(())
 }
 
-// Ensure use_version is here as defined in previous steps
-pub fn use_version(version: &str) -> Result<()> {
-    let lang_dir = dirs::home_dir().context("No home")?.join(".mlvm").join("python");
-    let version_path = lang_dir.join(version);
-    
-    if !version_path.exists() {
-        bail!("Python {} not installed.", version);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::engine;
+    use std::fs;
+
+    #[test]
+    fn python_version_files_list_several_versions() {
+        let contents = "# pyenv local 3.12.1 3.11 system\n3.12.1\n3.11  # for tox\n\nsystem\n3.10 3.9\n";
+        assert_eq!(Python.parse_version_file(contents), ["3.12.1", "3.11", "3.10", "3.9"]);
+        assert!(Python.parse_version_file("system\n# nothing pinned\n").is_empty());
     }
 
-    let current_link = lang_dir.join("current");
-    if current_link.exists() || current_link.symlink_metadata().is_ok() {
-        if current_link.is_dir() { fs::remove_dir_all(&current_link).ok(); } 
-        else { fs::remove_file(&current_link).ok(); }
-    }
-
-    #[cfg(unix)]
-    std::os::unix::fs::symlink(&version_path, &current_link)?;
-    #[cfg(windows)]
-    std::os::windows::fs::symlink_dir(&version_path, &current_link)?;
+    #[test]
+    fn a_project_asks_for_every_listed_version() {
+        let project = tempfile::tempdir().unwrap();
+        fs::write(project.path().join(".python-version"), "3.12 # default\n3.11\n").unwrap();
 
-    println!("Switched to Python {}", version);
-    println!("Ensure {} is in your PATH.", current_link.display());
-    Ok(())
-}
\ No newline at end of file
+        let found = engine::project_version(&Python, project.path()).unwrap();
+        assert_eq!(found.specs, ["3.12", "3.11"]);
+    }
+}
@@ -0,0 +1,315 @@
+// src/commands/resolve.rs
+//
+// Turns what the user typed (`18`, `18.x`, `^3.11`, `~1.21`, `latest`, `lts/*`,
+// `lts/hydrogen`, `lts/-1`, `v20.11.0`, ...) into one concrete version, picked from
+// either the remote index (install) or the installed versions (use).
+use semver::{Version, VersionReq};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionSpec {
+    /// `latest`, `node` or `stable`: the newest stable release.
+    Latest,
+    /// `lts/*` (`None`) or `lts/<codename>`.
+    Lts(Option<String>),
+    /// `lts/-1`: the LTS line before the newest one, `lts/-2` the one before that, ...
+    LtsBack(usize),
+    /// A partial version (`18`, `3.11`), an x-range (`18.x`, `3.11.x`, `*`) or a semver
+    /// range (`^3.11`, `~1.21`, `>=1.20 <1.22`).
+    Req(VersionReq),
+    /// Anything else, compared literally (e.g. `1.22rc1`).
+    Exact(String),
+}
+
+/// A version that can be picked, with the LTS codename when the upstream has one.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub version: String,
+    pub lts: Option<String>,
+}
+
+impl VersionSpec {
+    pub fn parse(input: &str) -> Self {
+        let input = input.trim();
+        match input.to_ascii_lowercase().as_str() {
+            "latest" | "node" | "stable" => return VersionSpec::Latest,
+            "lts" | "lts/*" => return VersionSpec::Lts(None),
+            lower => {
+                if let Some(codename) = lower.strip_prefix("lts/") {
+                    if let Some(back) = codename.strip_prefix('-').and_then(|n| n.parse().ok()) {
+                        return VersionSpec::LtsBack(back);
+                    }
+                    return VersionSpec::Lts(Some(codename.to_string()));
+                }
+            }
+        }
+
+        // `18`, `v18.17`, `3.11.5`: a partial version means "the newest release starting with it",
+        // which is what semver's `=` operator does for missing components. An x-range (`18.x`,
+        // `18.x.x`) is the same partial version spelled out, and `*` or `x` alone is any release.
+        let bare = input.trim_start_matches('v');
+        let parts: Vec<&str> = bare.split('.').collect();
+        let given = parts.iter().take_while(|p| is_number(p)).count();
+        if parts.len() <= 3 && parts[given..].iter().all(|p| matches!(*p, "x" | "X" | "*")) {
+            let req = match given {
+                0 => VersionReq::parse("*"),
+                _ => VersionReq::parse(&format!("={}", parts[..given].join("."))),
+            };
+            if let Ok(req) = req {
+                return VersionSpec::Req(req);
+            }
+        }
+
+        if input.starts_with(['^', '~', '>', '<', '='])
+            && let Ok(req) = VersionReq::parse(&comma_separated(input))
+        {
+            return VersionSpec::Req(req);
+        }
+
+        VersionSpec::Exact(input.to_string())
+    }
+
+    /// The full version string when the input names exactly one release (`18.17.1`, `1.22rc1`).
+    pub fn exact(&self) -> Option<String> {
+        match self {
+            VersionSpec::Req(req) => match req.comparators.as_slice() {
+                [c] if c.op == semver::Op::Exact && c.minor.is_some() && c.patch.is_some() => {
+                    Some(format!("{}.{}.{}", c.major, c.minor?, c.patch?))
+                }
+                _ => None,
+            },
+            VersionSpec::Exact(v) if is_full_version(v) => Some(v.clone()),
+            _ => None,
+        }
+    }
+
+    /// Picks the best match, i.e. the newest candidate satisfying the spec.
+    /// `normalize` maps both sides to the same spelling for `Exact` comparisons.
+    pub fn pick<'a>(
+        &self,
+        candidates: &'a [Candidate],
+        normalize: impl Fn(&str) -> String,
+    ) -> Option<&'a Candidate> {
+        if let VersionSpec::LtsBack(back) = self {
+            let codename = lts_lines(candidates).into_iter().nth(*back)?;
+            return VersionSpec::Lts(Some(codename)).pick(candidates, normalize);
+        }
+        let matches = |c: &Candidate| -> bool {
+            let parsed = parse_version(&c.version);
+            match self {
+                VersionSpec::Latest => parsed.is_some_and(|v| v.pre.is_empty()),
+                VersionSpec::Lts(None) => c.lts.is_some(),
+                VersionSpec::Lts(Some(name)) => {
+                    c.lts.as_deref().is_some_and(|lts| lts.eq_ignore_ascii_case(name))
+                }
+                VersionSpec::LtsBack(_) => false,
+                VersionSpec::Req(req) => parsed.is_some_and(|v| req.matches(&v)),
+                VersionSpec::Exact(v) => normalize(v) == normalize(&c.version),
+            }
+        };
+
+        candidates
+            .iter()
+            .filter(|c| matches(c))
+            .max_by(|a, b| compare_versions(&a.version, &b.version))
+    }
+}
+
+/// The LTS codenames among `candidates`, the line with the newest release first.
+fn lts_lines(candidates: &[Candidate]) -> Vec<String> {
+    let mut releases: Vec<&Candidate> = candidates.iter().filter(|c| c.lts.is_some()).collect();
+    releases.sort_by(|a, b| compare_versions(&b.version, &a.version));
+    let mut lines: Vec<String> = Vec::new();
+    for codename in releases.into_iter().filter_map(|c| c.lts.as_deref()) {
+        if !lines.iter().any(|line| line.eq_ignore_ascii_case(codename)) {
+            lines.push(codename.to_string());
+        }
+    }
+    lines
+}
+
+fn is_number(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// npm separates the comparators of a range with spaces (`>=1.20 <1.22`), semver with commas.
+fn comma_separated(range: &str) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for token in range.replace('v', "").replace(',', " ").split_whitespace() {
+        match parts.last_mut() {
+            // `>= 1.20`: an operator on its own belongs to the next token.
+            Some(last) if last.chars().all(|c| "<>=~^".contains(c)) => last.push_str(token),
+            _ => parts.push(token.to_string()),
+        }
+    }
+    parts.join(", ")
+}
+
+/// Whether `raw` names one release in full: `1.2.3`, or a pre-release such as `1.22rc1`,
+/// `3.13.0a2` or `1.1.0-canary.1`. Rejects things like `18.x` that merely parse.
+fn is_full_version(raw: &str) -> bool {
+    let s = raw.trim_start_matches("bun-").trim_start_matches("go").trim_start_matches('v');
+    let numeric_end = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (numeric, pre) = s.split_at(numeric_end);
+    let parts: Vec<&str> = numeric.split('.').collect();
+    if !parts.iter().all(|p| is_number(p)) {
+        return false;
+    }
+    let pre = pre.strip_prefix(['-', '.']).unwrap_or(pre);
+    match parts.len() {
+        3 => pre.is_empty() || is_pre_release(pre),
+        // Go and Python name pre-releases of `x.y` without a patch (`1.22rc1`).
+        2 => is_pre_release(pre),
+        _ => false,
+    }
+}
+
+/// `rc1`, `a2`, `beta.2`, `canary.1`: dot-separated alphanumerics starting with a letter.
+fn is_pre_release(pre: &str) -> bool {
+    pre.starts_with(|c: char| c.is_ascii_alphabetic())
+        && pre.split('.').all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_alphanumeric()))
+}
+
+/// Leniently parses the version spellings used by our upstreams:
+/// `v20.11.0`, `go1.21.5`, `1.20` (no patch), `1.22rc1`, `3.13.0rc2`, `bun-v1.0.25`.
+pub fn parse_version(raw: &str) -> Option<Version> {
+    let s = raw.trim_start_matches("bun-").trim_start_matches("go").trim_start_matches('v');
+
+    // Split the leading `1.2.3` from whatever follows it (`rc1`, `-beta.2`, ...).
+    let numeric_end = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (numeric, pre) = s.split_at(numeric_end);
+    let numeric = numeric.trim_end_matches('.');
+
+    let mut parts = numeric.split('.').map(|p| p.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let mut version = Version::new(major, minor, patch);
+    let pre = pre.trim_start_matches(['-', '.']);
+    if !pre.is_empty() {
+        version.pre = semver::Prerelease::new(pre).ok()?;
+    }
+    Some(version)
+}
+
+/// Orders version strings by their parsed value, falling back to plain string order.
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    match (parse_version(a), parse_version(b)) {
+        (Some(va), Some(vb)) => va.cmp(&vb),
+        (Some(_), None) => std::cmp::Ordering::Greater,
+        (None, Some(_)) => std::cmp::Ordering::Less,
+        (None, None) => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(versions: &[(&str, Option<&str>)]) -> Vec<Candidate> {
+        versions
+            .iter()
+            .map(|(version, lts)| Candidate { version: version.to_string(), lts: lts.map(str::to_string) })
+            .collect()
+    }
+
+    fn pick(spec: &str, list: &[Candidate]) -> Option<String> {
+        VersionSpec::parse(spec).pick(list, |v| v.trim_start_matches('v').to_string()).map(|c| c.version.clone())
+    }
+
+    #[test]
+    fn picks_newest_match() {
+        let node = candidates(&[
+            ("v22.1.0", None),
+            ("v20.12.2", Some("Iron")),
+            ("v20.11.0", Some("Iron")),
+            ("v18.20.2", Some("Hydrogen")),
+            ("v23.0.0-rc.1", None),
+        ]);
+        assert_eq!(pick("18", &node).as_deref(), Some("v18.20.2"));
+        assert_eq!(pick("v20.11", &node).as_deref(), Some("v20.11.0"));
+        assert_eq!(pick("20.11.0", &node).as_deref(), Some("v20.11.0"));
+        assert_eq!(pick("^20", &node).as_deref(), Some("v20.12.2"));
+        assert_eq!(pick("~20.11", &node).as_deref(), Some("v20.11.0"));
+        assert_eq!(pick("21", &node), None);
+    }
+
+    #[test]
+    fn x_ranges_are_partial_versions() {
+        let node = candidates(&[("v22.1.0", None), ("v20.12.2", None), ("v20.11.0", None), ("v23.0.0-rc.1", None)]);
+        assert_eq!(pick("20.x", &node).as_deref(), Some("v20.12.2"));
+        assert_eq!(pick("v20.x.x", &node).as_deref(), Some("v20.12.2"));
+        assert_eq!(pick("20.11.x", &node).as_deref(), Some("v20.11.0"));
+        assert_eq!(pick("20.X", &node).as_deref(), Some("v20.12.2"));
+        // Any release, but no pre-release.
+        assert_eq!(pick("*", &node).as_deref(), Some("v22.1.0"));
+        assert_eq!(pick("x", &node).as_deref(), Some("v22.1.0"));
+        assert_eq!(pick("21.x", &node), None);
+        // Only trailing components can be wildcards.
+        assert_eq!(VersionSpec::parse("20.x.1"), VersionSpec::Exact("20.x.1".to_string()));
+    }
+
+    #[test]
+    fn aliases() {
+        let node = candidates(&[("v22.1.0", None), ("v20.12.2", Some("Iron")), ("v18.20.2", Some("Hydrogen"))]);
+        assert_eq!(VersionSpec::parse("node"), VersionSpec::Latest);
+        assert_eq!(VersionSpec::parse("LTS/*"), VersionSpec::Lts(None));
+        assert_eq!(pick("latest", &node).as_deref(), Some("v22.1.0"));
+        assert_eq!(pick("stable", &node).as_deref(), Some("v22.1.0"));
+        assert_eq!(pick("lts", &node).as_deref(), Some("v20.12.2"));
+        assert_eq!(pick("lts/hydrogen", &node).as_deref(), Some("v18.20.2"));
+        assert_eq!(pick("lts/argon", &node), None);
+    }
+
+    #[test]
+    fn lts_lines_counted_back() {
+        let node = candidates(&[
+            ("v22.1.0", None),
+            ("v20.12.2", Some("Iron")),
+            ("v20.11.0", Some("Iron")),
+            ("v18.20.2", Some("Hydrogen")),
+            ("v16.20.2", Some("Gallium")),
+        ]);
+        assert_eq!(VersionSpec::parse("lts/-1"), VersionSpec::LtsBack(1));
+        assert_eq!(pick("lts/-0", &node).as_deref(), Some("v20.12.2"));
+        assert_eq!(pick("lts/-1", &node).as_deref(), Some("v18.20.2"));
+        assert_eq!(pick("lts/-2", &node).as_deref(), Some("v16.20.2"));
+        assert_eq!(pick("lts/-3", &node), None);
+    }
+
+    #[test]
+    fn space_separated_ranges() {
+        let go = candidates(&[("1.22.2", None), ("1.21.9", None), ("1.20.14", None), ("1.19.13", None)]);
+        assert_eq!(pick(">=1.20 <1.22", &go).as_deref(), Some("1.21.9"));
+        assert_eq!(pick(">= 1.20, < 1.21", &go).as_deref(), Some("1.20.14"));
+        assert_eq!(pick(">=v1.19 <v1.20", &go).as_deref(), Some("1.19.13"));
+    }
+
+    #[test]
+    fn exact_only_for_full_versions() {
+        let exact = |s: &str| VersionSpec::parse(s).exact();
+        assert_eq!(exact("18.17.1").as_deref(), Some("18.17.1"));
+        assert_eq!(exact("v18.17.1").as_deref(), Some("18.17.1"));
+        assert_eq!(exact("1.22rc1").as_deref(), Some("1.22rc1"));
+        assert_eq!(exact("3.13.0a2").as_deref(), Some("3.13.0a2"));
+        assert_eq!(exact("1.1.0-canary.1").as_deref(), Some("1.1.0-canary.1"));
+        for partial in ["18", "18.17", "18.x", "18.x.1", "^18", "lts/*", "latest", "1.2.3.4", "next"] {
+            assert_eq!(exact(partial), None, "{partial}");
+        }
+    }
+
+    #[test]
+    fn orders_upstream_spellings() {
+        assert_eq!(parse_version("go1.21.5"), Some(Version::new(1, 21, 5)));
+        assert_eq!(parse_version("bun-v1.0.25"), Some(Version::new(1, 0, 25)));
+        assert_eq!(compare_versions("1.22rc1", "1.22.0"), std::cmp::Ordering::Less);
+        assert_eq!(compare_versions("v20.9.0", "v20.10.0"), std::cmp::Ordering::Less);
+    }
+}
@@ -0,0 +1,530 @@
+// src/commands/shell.rs
+//
+// Shell integration. `mlvm env` prints the code that activates mlvm in a
+// shell: the shim directory at the front of PATH, plus the variables some
+// toolchains expect (`GOROOT`). The code is idempotent, so sourcing it again
+// (a reloaded rc file, a nested shell) never adds a second PATH entry.
+//
+// `mlvm init` adds a hook that runs `mlvm hook-env` whenever the shell changes
+// directory. It looks for project version files (`.node-version`, ...) and
+// selects the versions they ask for through `MLVM_<LANG>_VERSION`, which the
+// shims read. That only affects the shell session; `current` stays as it is.
+// The variables the hook set are listed in `MLVM_HOOK_VARS`, so they can be
+// dropped again on leaving the project.
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{BufRead, IsTerminal, Write};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Shell;
+use crate::commands::bun::Bun;
+use crate::commands::config::{self, AutoInstall};
+use crate::commands::engine;
+use crate::commands::error::{self, ErrorKind};
+use crate::commands::go::Go;
+use crate::commands::node::Node;
+use crate::commands::output::{self, status};
+use crate::commands::python::Python;
+use crate::commands::shims;
+use crate::commands::toolchain::Toolchain;
+
+/// `env`: what the printed code sets.
+#[derive(Serialize, Debug)]
+pub struct Activation {
+    pub shell: &'static str,
+    /// Prepended to PATH unless already there.
+    pub path: Vec<PathBuf>,
+    pub vars: BTreeMap<&'static str, PathBuf>,
+    /// Variables mlvm set for a version that is no longer selected.
+    pub unset: Vec<&'static str>,
+}
+
+/// `env`
+pub fn env(shell: Option<Shell>) -> Result<()> {
+    let shell = shell.unwrap_or_else(detect);
+    // stdout is evaluated by the shell.
+    output::status_to_stderr();
+    let activation = activation(shell)?;
+    output::emit(&activation, |a| print!("{}", script(shell, a)))
+}
+
+/// The activation for the current selections.
+pub fn activation(shell: Shell) -> Result<Activation> {
+    let mut vars = BTreeMap::new();
+    let mut unset = Vec::new();
+    add_vars(&Node, session_version_or_warn(&Node), &mut vars, &mut unset)?;
+    add_vars(&Python, session_version_or_warn(&Python), &mut vars, &mut unset)?;
+    add_vars(&Go, session_version_or_warn(&Go), &mut vars, &mut unset)?;
+    add_vars(&Bun, session_version_or_warn(&Bun), &mut vars, &mut unset)?;
+    Ok(Activation { shell: name(shell), path: vec![shims::shims_dir()?], vars, unset })
+}
+
+/// The version `MLVM_<LANG>_VERSION` selects, if it is set.
+fn session_version<T: Toolchain>(toolchain: &T) -> Result<Option<String>> {
+    if env::var(engine::version_var::<T>()).is_ok_and(|v| !v.trim().is_empty()) {
+        Ok(engine::selected_versions(toolchain)?.into_iter().next())
+    } else {
+        Ok(None)
+    }
+}
+
+/// `session_version`, where a variable naming no installed version only costs that language
+/// a warning: it then falls back to `current`.
+fn session_version_or_warn<T: Toolchain>(toolchain: &T) -> Option<String> {
+    session_version(toolchain).unwrap_or_else(|e| {
+        status!("mlvm: ignoring {} ({:#})", engine::version_var::<T>(), e);
+        None
+    })
+}
+
+/// The variables of one language for `version`. Without a session version they
+/// point into `current`, so they stay right across `mlvm use`. With neither, the
+/// variables that still point into mlvm are unset.
+fn add_vars<T: Toolchain>(
+    toolchain: &T,
+    version: Option<String>,
+    vars: &mut BTreeMap<&'static str, PathBuf>,
+    unset: &mut Vec<&'static str>,
+) -> Result<()> {
+    let lang_dir = engine::lang_dir::<T>()?;
+    let root = match version {
+        Some(version) => Some(lang_dir.join(version)),
+        None => engine::current_version::<T>()?.map(|_| lang_dir.join(engine::CURRENT_LINK)),
+    };
+    match root {
+        Some(root) => vars.extend(toolchain.env_vars(&root)),
+        None => unset.extend(stale_vars::<T>(&lang_dir, |name| env::var_os(name))),
+    }
+    Ok(())
+}
+
+/// Those of the language's variables that point into `lang_dir`. A variable pointing
+/// elsewhere was set by the user and is left alone.
+fn stale_vars<T: Toolchain>(lang_dir: &Path, var: impl Fn(&str) -> Option<OsString>) -> Vec<&'static str> {
+    T::ENV_VARS
+        .iter()
+        .copied()
+        .filter(|name| var(name).is_some_and(|value| Path::new(&value).starts_with(lang_dir)))
+        .collect()
+}
+
+/// The shell mlvm runs under, from `$SHELL`. PowerShell does not set it.
+pub fn detect() -> Shell {
+    let shell = env::var("SHELL").unwrap_or_default();
+    let name = shell.rsplit(['/', '\\']).next().unwrap_or_default().trim_end_matches(".exe");
+    match name {
+        "zsh" => Shell::Zsh,
+        "fish" => Shell::Fish,
+        "nu" => Shell::Nu,
+        "pwsh" | "powershell" => Shell::Pwsh,
+        "bash" | "sh" => Shell::Bash,
+        _ if cfg!(windows) => Shell::Pwsh,
+        _ => Shell::Bash,
+    }
+}
+
+pub fn name(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash => "bash",
+        Shell::Zsh => "zsh",
+        Shell::Fish => "fish",
+        Shell::Pwsh => "pwsh",
+        Shell::Nu => "nu",
+    }
+}
+
+/// The line that goes into the shell's startup file.
+pub fn setup_line(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash | Shell::Zsh => r#"eval "$(mlvm env)""#,
+        Shell::Fish => "mlvm env --shell fish | source",
+        Shell::Pwsh => "mlvm env --shell pwsh | Out-String | Invoke-Expression",
+        Shell::Nu => "mlvm env --shell nu | save -f ~/.mlvm/env.nu  # then `source ~/.mlvm/env.nu` in config.nu",
+    }
+}
+
+/// The code `env` prints.
+pub fn script(shell: Shell, activation: &Activation) -> String {
+    let mut out = String::new();
+    for dir in activation.path.iter().rev() {
+        let dir = quote(shell, &dir.to_string_lossy());
+        out += &match shell {
+            Shell::Bash | Shell::Zsh => {
+                format!("case \":$PATH:\" in *:{dir}:*) ;; *) export PATH={dir}\":$PATH\" ;; esac\n")
+            }
+            Shell::Fish => format!("contains -- {dir} $PATH; or set -gx PATH {dir} $PATH\n"),
+            Shell::Pwsh => format!(
+                "if (-not (($env:PATH -split [IO.Path]::PathSeparator) -contains {dir})) \
+                {{ $env:PATH = {dir} + [IO.Path]::PathSeparator + $env:PATH }}\n"
+            ),
+            Shell::Nu => format!(
+                "$env.PATH = ($env.PATH | split row (char esep) | where {{|p| $p != {dir} }} | prepend {dir})\n"
+            ),
+        };
+    }
+    for name in &activation.unset {
+        out += &unset_var(shell, name);
+    }
+    for (name, value) in &activation.vars {
+        out += &set_var(shell, name, &value.to_string_lossy());
+    }
+    out
+}
+
+/// Quotes a string literal for `shell`.
+fn quote(shell: Shell, s: &str) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => format!("'{}'", s.replace('\'', r"'\''")),
+        Shell::Fish => format!("'{}'", s.replace('\\', r"\\").replace('\'', r"\'")),
+        Shell::Pwsh => format!("'{}'", s.replace('\'', "''")),
+        Shell::Nu => format!("\"{}\"", s.replace('\\', r"\\").replace('"', "\\\"")),
+    }
+}
+
+/// `init`: the code for the shell's startup file.
+#[derive(Serialize, Debug)]
+pub struct Init {
+    pub shell: &'static str,
+    pub script: String,
+}
+
+/// `init`
+pub fn init(shell: Option<Shell>) -> Result<()> {
+    let shell = shell.unwrap_or_else(detect);
+    let mut script = script(shell, &activation(shell)?);
+    script += &hook(shell, &hook_command(shell)?);
+    output::emit(&Init { shell: name(shell), script }, |init| print!("{}", init.script))
+}
+
+/// How the hook starts mlvm: this binary, with this mlvm home.
+fn hook_command(shell: Shell) -> Result<String> {
+    let exe = env::current_exe()?.to_string_lossy().to_string();
+    let home = engine::mlvm_dir()?.to_string_lossy().to_string();
+    Ok(match shell {
+        Shell::Pwsh => format!("& {} --home {}", quote(shell, &exe), quote(shell, &home)),
+        Shell::Nu => format!("run-external {} \"--home\" {}", quote(shell, &exe), quote(shell, &home)),
+        _ => format!("{} --home {}", quote(shell, &exe), quote(shell, &home)),
+    })
+}
+
+/// Runs `hook-env` on every change of directory, and once right away.
+fn hook(shell: Shell, mlvm: &str) -> String {
+    match shell {
+        Shell::Bash => format!(
+            r#"_mlvm_hook() {{
+  if [ "${{_MLVM_PWD-}}" != "$PWD" ]; then
+    _MLVM_PWD=$PWD
+    eval "$({mlvm} hook-env --shell bash)"
+  fi
+}}
+case ";${{PROMPT_COMMAND-}};" in
+  *";_mlvm_hook;"*) ;;
+  *) PROMPT_COMMAND="_mlvm_hook${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}" ;;
+esac
+"#
+        ),
+        Shell::Zsh => format!(
+            r#"_mlvm_hook() {{
+  eval "$({mlvm} hook-env --shell zsh)"
+}}
+autoload -Uz add-zsh-hook
+add-zsh-hook chpwd _mlvm_hook
+_mlvm_hook
+"#
+        ),
+        Shell::Fish => format!(
+            r#"function _mlvm_hook --on-variable PWD
+    {mlvm} hook-env --shell fish | source
+end
+_mlvm_hook
+"#
+        ),
+        Shell::Pwsh => format!(
+            r#"if (-not $global:_MlvmPrompt) {{ $global:_MlvmPrompt = $function:prompt }}
+function global:prompt {{
+    if ($global:_MlvmPwd -ne $PWD.Path) {{
+        $global:_MlvmPwd = $PWD.Path
+        {mlvm} hook-env --shell pwsh | Out-String | Invoke-Expression
+    }}
+    & $global:_MlvmPrompt
+}}
+"#
+        ),
+        // Nushell cannot evaluate code at runtime; the hook loads a record instead.
+        Shell::Nu => format!(
+            r#"$env.config = ($env.config | upsert hooks.env_change.PWD {{|config|
+    let hooks = ($config | get -o hooks.env_change.PWD | default [])
+    $hooks | append {{|before, after| {mlvm} "hook-env" "--shell" "nu" | from json | load-env }}
+}})
+"#
+        ),
+    }
+}
+
+/// What `hook-env` changes in the session.
+#[derive(Serialize, Debug, Default)]
+pub struct HookChanges {
+    pub set: BTreeMap<String, String>,
+    pub unset: Vec<String>,
+}
+
+const HOOK_VARS: &str = "MLVM_HOOK_VARS";
+
+/// `hook-env`: selects the versions the project in the current directory asks for.
+pub async fn hook_env(shell: Option<Shell>) -> Result<()> {
+    let shell = shell.unwrap_or_else(detect);
+    // stdout is evaluated by the shell.
+    output::status_to_stderr();
+
+    let owned: Vec<String> = env::var(HOOK_VARS)
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    let auto_install = config::load()?.shell.auto_install.unwrap_or_default();
+    let mut changes = HookChanges::default();
+    let mut vars = BTreeMap::new();
+    let mut unset = Vec::new();
+    hook_language(&Node, &owned, auto_install, &mut changes, &mut vars, &mut unset).await?;
+    hook_language(&Python, &owned, auto_install, &mut changes, &mut vars, &mut unset).await?;
+    hook_language(&Go, &owned, auto_install, &mut changes, &mut vars, &mut unset).await?;
+    hook_language(&Bun, &owned, auto_install, &mut changes, &mut vars, &mut unset).await?;
+    changes.unset.extend(unset.into_iter().map(str::to_string));
+    for (name, value) in vars {
+        changes.set.insert(name.to_string(), value.to_string_lossy().to_string());
+    }
+
+    let now_owned: Vec<&str> = changes
+        .set
+        .keys()
+        .filter(|name| name.starts_with("MLVM_") && name.ends_with("_VERSION"))
+        .map(String::as_str)
+        .collect();
+    if now_owned.is_empty() {
+        if !owned.is_empty() {
+            changes.unset.push(HOOK_VARS.to_string());
+        }
+    } else {
+        let now_owned = now_owned.join(" ");
+        changes.set.insert(HOOK_VARS.to_string(), now_owned);
+    }
+    print!("{}", changes_script(shell, &changes));
+    Ok(())
+}
+
+async fn hook_language<T: Toolchain>(
+    toolchain: &T,
+    owned: &[String],
+    auto_install: AutoInstall,
+    changes: &mut HookChanges,
+    vars: &mut BTreeMap<&'static str, PathBuf>,
+    unset: &mut Vec<&'static str>,
+) -> Result<()> {
+    let var = engine::version_var::<T>();
+    let project = engine::project_version(toolchain, &env::current_dir()?);
+
+    let mut selected = Vec::new();
+    if let Some(project) = &project {
+        for spec in &project.specs {
+            match engine::resolve_installed(toolchain, spec) {
+                Ok(version) => selected.push(version),
+                Err(e) if error::kind(&e) == ErrorKind::NotInstalled => {
+                    if let Some(version) = offer_install(toolchain, spec, project, auto_install).await {
+                        selected.push(version);
+                    }
+                }
+                Err(e) => status!("mlvm: ignoring {} ({:#})", project.file.display(), e),
+            }
+        }
+    }
+
+    let version = if !selected.is_empty() {
+        changes.set.insert(var, selected.join(":"));
+        selected.into_iter().next()
+    } else if owned.contains(&var) {
+        // Left the project: back to `current`.
+        changes.unset.push(var);
+        None
+    } else {
+        // Never touch a selection the user made by hand.
+        session_version_or_warn(toolchain)
+    };
+    add_vars(toolchain, version, vars, unset)
+}
+
+/// Handles a version file asking for a version that is not installed, as
+/// `shell.auto_install` says. Returns the installed version.
+async fn offer_install<T: Toolchain>(
+    toolchain: &T,
+    spec: &str,
+    project: &engine::ProjectVersion,
+    auto_install: AutoInstall,
+) -> Option<String> {
+    let wanted = format!("{} asks for {} {}", project.file.display(), T::DISPLAY_NAME, spec);
+    let install = match auto_install {
+        AutoInstall::Never => false,
+        AutoInstall::Always => true,
+        AutoInstall::Ask => std::io::stdin().is_terminal() && confirm(&format!("mlvm: {}. Install it now? [y/N] ", wanted)),
+    };
+    if !install {
+        status!("mlvm: {}, which is not installed. Run `mlvm {} install {}`.", wanted, T::NAME, spec);
+        return None;
+    }
+    match engine::install(toolchain, spec, false).await {
+        Ok(report) => Some(report.version),
+        Err(e) => {
+            status!("mlvm: could not install {} {}: {:#}", T::DISPLAY_NAME, spec, e);
+            None
+        }
+    }
+}
+
+fn confirm(question: &str) -> bool {
+    eprint!("{}", question);
+    std::io::stderr().flush().ok();
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).is_ok() && matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+/// The code that applies `changes`. Nushell gets a record for `load-env`, where an
+/// empty value stands for an unset variable.
+fn changes_script(shell: Shell, changes: &HookChanges) -> String {
+    if shell == Shell::Nu {
+        let mut record: BTreeMap<&str, &str> = changes.unset.iter().map(|name| (name.as_str(), "")).collect();
+        record.extend(changes.set.iter().map(|(name, value)| (name.as_str(), value.as_str())));
+        return serde_json::to_string(&record).unwrap_or_default() + "\n";
+    }
+    let mut out = String::new();
+    for name in &changes.unset {
+        out += &unset_var(shell, name);
+    }
+    for (name, value) in &changes.set {
+        out += &set_var(shell, name, value);
+    }
+    out
+}
+
+fn unset_var(shell: Shell, name: &str) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => format!("unset {name}\n"),
+        Shell::Fish => format!("set -e {name}\n"),
+        Shell::Pwsh => format!("Remove-Item Env:{name} -ErrorAction SilentlyContinue\n"),
+        Shell::Nu => format!("hide-env -i {name}\n"),
+    }
+}
+
+fn set_var(shell: Shell, name: &str, value: &str) -> String {
+    let value = quote(shell, value);
+    match shell {
+        Shell::Bash | Shell::Zsh => format!("export {name}={value}\n"),
+        Shell::Fish => format!("set -gx {name} {value}\n"),
+        Shell::Pwsh => format!("$env:{name} = {value}\n"),
+        Shell::Nu => format!("$env.{name} = {value}\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activation(shims: &str) -> Activation {
+        Activation {
+            shell: "bash",
+            path: vec![PathBuf::from(shims)],
+            vars: BTreeMap::from([("GOROOT", PathBuf::from("/home/me/.mlvm/go/current/go"))]),
+            unset: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn env_prepends_the_shims_and_sets_the_variables() {
+        let activation = activation("/home/me/.mlvm/shims");
+        let bash = script(Shell::Bash, &activation);
+        assert!(bash.contains("export PATH='/home/me/.mlvm/shims'\":$PATH\""));
+        assert!(bash.ends_with("export GOROOT='/home/me/.mlvm/go/current/go'\n"));
+        assert_eq!(script(Shell::Zsh, &activation), bash);
+
+        let fish = script(Shell::Fish, &activation);
+        assert!(fish.starts_with("contains -- '/home/me/.mlvm/shims' $PATH; or set -gx PATH"));
+        assert!(fish.ends_with("set -gx GOROOT '/home/me/.mlvm/go/current/go'\n"));
+        assert!(script(Shell::Pwsh, &activation).ends_with("$env:GOROOT = '/home/me/.mlvm/go/current/go'\n"));
+        assert!(script(Shell::Nu, &activation).contains("where {|p| $p != \"/home/me/.mlvm/shims\" }"));
+    }
+
+    #[test]
+    fn env_quotes_paths_for_the_shell() {
+        let activation = activation("/home/o'neil/.mlvm/shims");
+        assert!(script(Shell::Bash, &activation).contains(r"'/home/o'\''neil/.mlvm/shims'"));
+        assert!(script(Shell::Fish, &activation).contains(r"'/home/o\'neil/.mlvm/shims'"));
+        assert!(script(Shell::Pwsh, &activation).contains("'/home/o''neil/.mlvm/shims'"));
+    }
+
+    #[test]
+    fn only_variables_pointing_into_mlvm_are_stale() {
+        let lang_dir = Path::new("/home/me/.mlvm/go");
+        let goroot = |value: &'static str| move |_: &str| Some(OsString::from(value));
+        assert_eq!(stale_vars::<Go>(lang_dir, goroot("/home/me/.mlvm/go/current/go")), ["GOROOT"]);
+        assert!(stale_vars::<Go>(lang_dir, goroot("/usr/local/go")).is_empty());
+        assert!(stale_vars::<Go>(lang_dir, |_| None).is_empty());
+        assert!(stale_vars::<Node>(lang_dir, goroot("/home/me/.mlvm/go/current/go")).is_empty());
+
+        let mut activation = activation("/home/me/.mlvm/shims");
+        activation.vars.clear();
+        activation.unset.push("GOROOT");
+        assert!(script(Shell::Bash, &activation).ends_with("unset GOROOT\n"));
+        assert!(script(Shell::Fish, &activation).ends_with("set -e GOROOT\n"));
+        assert!(script(Shell::Nu, &activation).ends_with("hide-env -i GOROOT\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sourcing_env_twice_adds_one_path_entry() {
+        let code = script(Shell::Bash, &activation("/opt/mlvm shims"));
+        let output = std::process::Command::new("sh")
+            .args(["-c", &format!("PATH=/usr/bin:/bin\n{code}{code}printf %s \"$PATH\"")])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "/opt/mlvm shims:/usr/bin:/bin");
+    }
+
+    #[test]
+    fn the_hook_runs_hook_env_for_its_shell() {
+        let mlvm = "'/usr/bin/mlvm' --home '/home/me/.mlvm'";
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::Pwsh] {
+            let command = format!("{mlvm} hook-env --shell {}", name(shell));
+            assert!(hook(shell, mlvm).contains(&command), "{}", name(shell));
+        }
+        // Sourcing the bash hook twice must not register it twice.
+        assert!(hook(Shell::Bash, mlvm).contains(r#"*";_mlvm_hook;"*) ;;"#));
+        assert!(hook(Shell::Nu, mlvm).contains(r#""hook-env" "--shell" "nu" | from json | load-env"#));
+        // `get -i` is deprecated in Nushell.
+        assert!(hook(Shell::Nu, mlvm).contains("get -o hooks.env_change.PWD"));
+    }
+
+    #[test]
+    fn hook_env_sets_and_unsets_variables() {
+        let changes = HookChanges {
+            set: BTreeMap::from([("MLVM_NODE_VERSION".to_string(), "v20.11.0".to_string())]),
+            unset: vec!["MLVM_GO_VERSION".to_string()],
+        };
+        assert_eq!(
+            changes_script(Shell::Bash, &changes),
+            "unset MLVM_GO_VERSION\nexport MLVM_NODE_VERSION='v20.11.0'\n"
+        );
+        assert_eq!(
+            changes_script(Shell::Fish, &changes),
+            "set -e MLVM_GO_VERSION\nset -gx MLVM_NODE_VERSION 'v20.11.0'\n"
+        );
+        assert_eq!(
+            changes_script(Shell::Pwsh, &changes),
+            "Remove-Item Env:MLVM_GO_VERSION -ErrorAction SilentlyContinue\n$env:MLVM_NODE_VERSION = 'v20.11.0'\n"
+        );
+        assert_eq!(
+            changes_script(Shell::Nu, &changes),
+            "{\"MLVM_GO_VERSION\":\"\",\"MLVM_NODE_VERSION\":\"v20.11.0\"}\n"
+        );
+    }
+}
@@ -0,0 +1,349 @@
+// src/commands/shims.rs
+//
+// `~/.mlvm/shims` holds one small launcher per tool (`node`, `npm`, `python3`,
+// `gofmt`, `bunx`, ...), so a single PATH entry serves every language. A shim
+// only calls `mlvm exec <language> <tool>`, which picks the version when the
+// tool runs (see `engine::selected_versions`) and replaces itself with it.
+//
+// The set of shims is every executable in the bin directories of every
+// installed version. It is rebuilt after installs and uninstalls, and by
+// `mlvm reshim`.
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::cli::Language;
+use crate::commands::bun::Bun;
+use crate::commands::engine;
+use crate::commands::error::{Error, ErrorKind};
+use crate::commands::go::Go;
+use crate::commands::lock;
+use crate::commands::node::Node;
+use crate::commands::output::{self, status};
+use crate::commands::python::Python;
+use crate::commands::toolchain::Toolchain;
+
+/// Remembers which mlvm binary the shims call, for rebuilds made through the library.
+const MLVM_PATH_FILE: &str = ".mlvm-path";
+
+/// `reshim`: the tools that have a shim now.
+#[derive(Serialize, Debug)]
+pub struct ShimList {
+    pub dir: PathBuf,
+    pub tools: Vec<String>,
+}
+
+/// `~/.mlvm/shims`
+pub fn shims_dir() -> Result<PathBuf> {
+    Ok(engine::mlvm_dir()?.join("shims"))
+}
+
+/// Whether the shim directory is on PATH already.
+pub fn on_path() -> Result<bool> {
+    let dir = shims_dir()?;
+    Ok(env::var_os("PATH").is_some_and(|path| env::split_paths(&path).any(|p| p == dir)))
+}
+
+/// `reshim`
+pub fn run() -> Result<()> {
+    let list = ShimList { tools: regenerate()?, dir: shims_dir()? };
+    output::emit(&list, |list| {
+        println!("{} shims in {}", list.tools.len(), list.dir.display());
+    })
+}
+
+/// Rebuilds the shims after an install or uninstall. A failure only costs the new
+/// shims, so it is reported instead of failing the command.
+pub fn refresh() {
+    if let Err(e) = regenerate() {
+        status!("WARNING: could not update the shims in ~/.mlvm/shims ({:#}). Run `mlvm reshim`.", e);
+    }
+}
+
+/// Writes a shim for every tool of every installed version and removes the rest.
+/// Returns the names of the tools.
+pub fn regenerate() -> Result<Vec<String>> {
+    let dir = shims_dir()?;
+    fs::create_dir_all(&dir)?;
+    let _lock = lock::shims(&dir)?;
+
+    // The first language to provide a tool name gets its shim.
+    let mut tools = BTreeMap::new();
+    collect_tools(&Node, Language::Node, &mut tools)?;
+    collect_tools(&Python, Language::Python, &mut tools)?;
+    collect_tools(&Go, Language::Go, &mut tools)?;
+    collect_tools(&Bun, Language::Bun, &mut tools)?;
+
+    let command = launch_command(&dir)?;
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with('.') && !tools.contains_key(shim_tool_name(&name)) {
+            fs::remove_file(entry.path()).ok();
+        }
+    }
+    for (tool, language) in &tools {
+        write_shim(&dir, tool, *language, &command)?;
+    }
+    Ok(tools.into_keys().collect())
+}
+
+fn collect_tools<T: Toolchain>(
+    toolchain: &T,
+    language: Language,
+    tools: &mut BTreeMap<String, Language>,
+) -> Result<()> {
+    let lang_dir = engine::lang_dir::<T>()?;
+    for version in engine::installed_versions::<T>()? {
+        for bin in toolchain.bin_dirs() {
+            let Ok(entries) = fs::read_dir(lang_dir.join(&version).join(bin)) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if let Some(tool) = tool_name(&entry.path()) {
+                    tools.entry(tool).or_insert(language);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The name a file in a bin directory is run by, if it is an executable.
+fn tool_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    if name.starts_with('.') {
+        return None;
+    }
+    // `metadata` follows symlinks such as `npm -> ../lib/node_modules/npm/bin/npm-cli.js`.
+    let meta = fs::metadata(path).ok()?;
+    if !meta.is_file() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        (meta.permissions().mode() & 0o111 != 0).then(|| name.to_string())
+    }
+    #[cfg(windows)]
+    {
+        let (stem, ext) = name.rsplit_once('.')?;
+        ["exe", "cmd", "bat"].contains(&ext.to_ascii_lowercase().as_str()).then(|| stem.to_string())
+    }
+}
+
+/// The tool a file in the shim directory stands for.
+fn shim_tool_name(file_name: &str) -> &str {
+    if cfg!(windows) {
+        file_name.strip_suffix(".cmd").unwrap_or(file_name)
+    } else {
+        file_name
+    }
+}
+
+/// How the shims start mlvm. The running binary when mlvm is the CLI; library callers
+/// reuse whatever the CLI recorded last, or fall back to `mlvm` on PATH.
+fn launch_command(dir: &Path) -> Result<String> {
+    let record = dir.join(MLVM_PATH_FILE);
+    let exe = if output::is_cli() {
+        let exe = env::current_exe().context("Could not find the mlvm executable")?;
+        fs::write(&record, exe.to_string_lossy().as_bytes())?;
+        Some(exe.to_string_lossy().to_string())
+    } else {
+        fs::read_to_string(&record).ok()
+    };
+    let home = engine::mlvm_dir()?.to_string_lossy().to_string();
+    Ok(match exe {
+        Some(exe) => format!("{} --home {}", quote(&exe), quote(&home)),
+        None => format!("mlvm --home {}", quote(&home)),
+    })
+}
+
+fn write_shim(dir: &Path, tool: &str, language: Language, command: &str) -> Result<()> {
+    let language = language_name(language);
+    let (path, script) = if cfg!(windows) {
+        (
+            dir.join(format!("{}.cmd", tool)),
+            format!("@echo off\r\nrem mlvm shim for {tool}. Rebuilt by mlvm; do not edit.\r\n{command} exec {language} {tool} -- %*\r\n"),
+        )
+    } else {
+        (
+            dir.join(tool),
+            format!("#!/bin/sh\n# mlvm shim for {tool}. Rebuilt by mlvm; do not edit.\nexec {command} exec {language} {tool} -- \"$@\"\n"),
+        )
+    };
+    if fs::read_to_string(&path).is_ok_and(|existing| existing == script) {
+        return Ok(());
+    }
+    fs::write(&path, script).with_context(|| format!("Failed to write {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+/// Quotes an argument for `sh` or `cmd`.
+fn quote(arg: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", arg)
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+fn language_name(language: Language) -> &'static str {
+    match language {
+        Language::Node => Node::NAME,
+        Language::Python => Python::NAME,
+        Language::Go => Go::NAME,
+        Language::Bun => Bun::NAME,
+    }
+}
+
+/// `mlvm exec`: runs the tool named first in `command`, with the rest as its arguments,
+/// from the selected version of `language`.
+pub fn exec(language: Language, command: Vec<OsString>) -> Result<()> {
+    let (tool, args) = split_command(command)?;
+    let tool = tool.as_str();
+    match language {
+        Language::Node => exec_tool(&Node, tool, args),
+        Language::Python => exec_tool(&Python, tool, args),
+        Language::Go => exec_tool(&Go, tool, args),
+        Language::Bun => exec_tool(&Bun, tool, args),
+    }
+}
+
+/// Splits `exec`'s arguments into the tool name and the tool's arguments, dropping the
+/// `--` the shims put between them.
+fn split_command(command: Vec<OsString>) -> Result<(String, Vec<OsString>)> {
+    let mut command = command.into_iter();
+    let tool = command.next().unwrap_or_default();
+    let Ok(tool) = tool.into_string() else {
+        bail!(Error::new(ErrorKind::InvalidInput, "The tool name is not valid UTF-8."));
+    };
+    let mut args: Vec<OsString> = command.collect();
+    if args.first().is_some_and(|arg| arg == "--") {
+        args.remove(0);
+    }
+    Ok((tool, args))
+}
+
+fn exec_tool<T: Toolchain>(toolchain: &T, tool: &str, args: Vec<OsString>) -> Result<()> {
+    let versions = engine::selected_versions(toolchain)?;
+    if versions.is_empty() {
+        bail!(Error::new(ErrorKind::NotInstalled, format!("No {} version is active.", T::DISPLAY_NAME))
+            .hint(format!("Pick one with `mlvm {} use <version>`.", T::NAME)));
+    }
+
+    let lang_dir = engine::lang_dir::<T>()?;
+    let found = versions.iter().find_map(|version| {
+        let root = lang_dir.join(version);
+        let bin_dirs: Vec<PathBuf> = toolchain.bin_dirs().into_iter().map(|bin| root.join(bin)).collect();
+        let program = bin_dirs.iter().find_map(|dir| find_executable(dir, tool))?;
+        Some((program, bin_dirs, root))
+    });
+    let Some((program, bin_dirs, root)) = found else {
+        bail!(Error::new(
+            ErrorKind::NotFound,
+            format!("{} {} has no `{}`.", T::DISPLAY_NAME, versions.join(", "), tool)
+        )
+        .hint("Run `mlvm reshim` to remove shims of tools that are gone."));
+    };
+
+    let path = tool_path(bin_dirs, env::var_os("PATH"));
+    let mut command = Command::new(&program);
+    command.args(args).env("PATH", env::join_paths(path)?).envs(toolchain.env_vars(&root));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let e = command.exec();
+        Err(e).with_context(|| format!("Failed to run {}", program.display()))
+    }
+    #[cfg(windows)]
+    {
+        let status = command.status().with_context(|| format!("Failed to run {}", program.display()))?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
+
+/// PATH for a tool: its version's bin directories, then `path` without them. Tools that start
+/// each other (`npm` runs `node`) get the same version, and nesting them does not grow PATH.
+fn tool_path(bin_dirs: Vec<PathBuf>, path: Option<OsString>) -> Vec<PathBuf> {
+    let rest: Vec<PathBuf> = path.map(|p| env::split_paths(&p).collect()).unwrap_or_default();
+    let mut path = bin_dirs;
+    for dir in rest {
+        if !path.contains(&dir) {
+            path.push(dir);
+        }
+    }
+    path
+}
+
+fn find_executable(dir: &Path, tool: &str) -> Option<PathBuf> {
+    let names: Vec<String> = if cfg!(windows) {
+        ["exe", "cmd", "bat"].iter().map(|ext| format!("{}.{}", tool, ext)).collect()
+    } else {
+        vec![tool.to_string()]
+    };
+    names.into_iter().map(|name| dir.join(name)).find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use clap::Parser;
+
+    /// What a shim passes on: `mlvm --home <dir> exec <language> <tool> -- "$@"`.
+    fn parse_exec(argv: &[&str]) -> (Cli, String, Vec<OsString>) {
+        let cli = Cli::try_parse_from(argv).unwrap();
+        let Commands::Exec { ref command, .. } = cli.command else { panic!("{:?}", cli.command) };
+        let (tool, args) = split_command(command.clone()).unwrap();
+        (cli, tool, args)
+    }
+
+    #[test]
+    fn tool_arguments_reach_the_tool_unchanged() {
+        let tool_args = ["-q", "--json", "--help", "--home", "elsewhere", "--", "-h"];
+        // Through a shim, and typed by hand without the separator.
+        for separator in [&["--"][..], &[][..]] {
+            let argv = [&["mlvm", "--home", "/h", "exec", "node", "node"][..], separator, &tool_args[..]].concat();
+            let (cli, tool, args) = parse_exec(&argv);
+            assert_eq!(tool, "node");
+            assert_eq!(args, tool_args.map(OsString::from), "{argv:?}");
+            assert!(!cli.quiet && !cli.json);
+            assert_eq!(cli.home.as_deref(), Some(Path::new("/h")));
+        }
+        // A `--` meant for the tool survives the shim's own.
+        let (_, _, args) = parse_exec(&["mlvm", "exec", "node", "npm", "--", "--", "run"]);
+        assert_eq!(args, ["--", "run"].map(OsString::from));
+    }
+
+    #[test]
+    fn nested_tools_do_not_grow_path() {
+        let bin = PathBuf::from("/mlvm/node/v20.11.0/bin");
+        let system = env::join_paths(["/usr/bin", "/bin"]).unwrap();
+        let outer = env::join_paths(tool_path(vec![bin.clone()], Some(system))).unwrap();
+        let inner = tool_path(vec![bin.clone()], Some(outer.clone()));
+        assert_eq!(env::join_paths(&inner).unwrap(), outer);
+        assert_eq!(inner, [bin, PathBuf::from("/usr/bin"), PathBuf::from("/bin")]);
+        assert_eq!(tool_path(Vec::new(), None), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn shims_separate_the_tool_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        write_shim(dir.path(), "node", Language::Node, "mlvm").unwrap();
+        let name = if cfg!(windows) { "node.cmd" } else { "node" };
+        let script = fs::read_to_string(dir.path().join(name)).unwrap();
+        let expected = if cfg!(windows) { "mlvm exec node node -- %*" } else { "exec mlvm exec node node -- \"$@\"" };
+        assert!(script.contains(expected), "{script}");
+    }
+}
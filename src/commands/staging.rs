@@ -0,0 +1,252 @@
+// src/commands/staging.rs
+//
+// Every install unpacks into its own `.staging-<pid>-<nanos>` directory inside
+// `~/.mlvm/<lang>`. The finished tree gets an install marker and is then moved
+// to `<version>` with a single rename, so a version directory is either whole
+// or absent. A version directory without the marker is never used.
+//
+// A staging directory holds a locked `.owner` file while its install runs.
+// If another run can take that lock, the install died and the directory is
+// deleted. Setting a directory up and judging it both happen under the
+// staging lock, so a directory is never judged before its owner is locked.
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::commands::lock;
+
+/// Name prefix of the per-install scratch directories.
+pub const STAGING_PREFIX: &str = ".staging-";
+/// Fixed scratch directories used before staging directories were unique.
+const LEGACY_STAGING_DIRS: [&str; 2] = ["temp_unpack", "temp"];
+/// Written into a version directory as the last step before it is promoted.
+pub const INSTALL_MARKER: &str = ".mlvm-installed";
+/// Present once the installs that predate the marker have been stamped.
+const MIGRATED_STAMP: &str = ".installs-marked";
+const OWNER_FILE: &str = ".owner";
+/// A staging directory without an owner file is only judged abandoned after this long,
+/// so one that is still being created is never removed.
+const UNOWNED_GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// A private scratch directory, removed again when dropped.
+pub struct Staging {
+    dir: PathBuf,
+    owner: Option<fs::File>,
+}
+
+impl Staging {
+    pub fn create(lang_dir: &Path) -> Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let dir = lang_dir.join(format!("{}{}-{}", STAGING_PREFIX, std::process::id(), nanos));
+        fs::create_dir_all(lang_dir)?;
+        let _lock = lock::staging(lang_dir)?;
+        fs::create_dir(&dir)
+            .with_context(|| format!("Failed to create staging directory {}", dir.display()))?;
+
+        let owner = fs::OpenOptions::new().write(true).create_new(true).open(dir.join(OWNER_FILE))?;
+        owner
+            .try_lock()
+            .map_err(std::io::Error::from)
+            .context("Failed to lock the staging directory")?;
+        Ok(Staging { dir, owner: Some(owner) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The unpacked tree that will become the install.
+    pub fn root(&self, root_dir: &str) -> Result<PathBuf> {
+        let source = self.dir.join(root_dir);
+        if root_dir.is_empty() || !source.is_dir() {
+            bail!("Extracted archive did not contain the expected '{}' folder.", root_dir);
+        }
+        Ok(source)
+    }
+
+    /// Marks `source` (from `root`) as a complete install and moves it to `target`
+    /// in one rename.
+    pub fn promote(&self, source: &Path, target: &Path) -> Result<()> {
+        fs::write(source.join(INSTALL_MARKER), b"")?;
+        rename_with_retry(source, target)
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        // Windows cannot delete a file that is still open.
+        drop(self.owner.take());
+        fs::remove_dir_all(&self.dir).ok();
+    }
+}
+
+/// Whether `version_dir` holds a complete install.
+pub fn is_complete(version_dir: &Path) -> bool {
+    version_dir.join(INSTALL_MARKER).is_file()
+}
+
+/// Whether `recover` has stamped the installs that predate the marker. Until then, every
+/// version directory is complete.
+pub fn is_migrated(lang_dir: &Path) -> bool {
+    lang_dir.join(MIGRATED_STAMP).exists()
+}
+
+/// Whether `name` is a scratch directory rather than a version.
+pub fn is_staging_dir(name: &str) -> bool {
+    name.starts_with(STAGING_PREFIX) || LEGACY_STAGING_DIRS.contains(&name)
+}
+
+/// Brings a language directory into a consistent state: removes the staging
+/// directories of installs that died, and stamps installs made before the marker existed.
+///
+/// Write failures are ignored so that a read-only mlvm home stays usable.
+pub fn recover(lang_dir: &Path) -> Result<()> {
+    if !lang_dir.exists() {
+        // Nothing to migrate in a language directory created by this release.
+        fs::create_dir_all(lang_dir)
+            .and_then(|_| fs::write(lang_dir.join(MIGRATED_STAMP), b""))
+            .ok();
+        return Ok(());
+    }
+    let entries = fs::read_dir(lang_dir)?;
+    let stamp = lang_dir.join(MIGRATED_STAMP);
+    let migrate = !stamp.exists();
+
+    let mut staging_dirs = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        if is_staging_dir(&name) {
+            staging_dirs.push((name, path));
+        } else if migrate && !name.starts_with('.') {
+            // Earlier releases promoted with a single rename too, so every version
+            // directory that already exists is complete.
+            fs::write(path.join(INSTALL_MARKER), b"").ok();
+        }
+    }
+
+    if migrate {
+        fs::write(stamp, b"").ok();
+    }
+    // Without the lock (a read-only home) nothing could be removed anyway.
+    if !staging_dirs.is_empty()
+        && let Ok(_lock) = lock::staging(lang_dir)
+    {
+        for (name, path) in staging_dirs {
+            if is_abandoned(&name, &path) {
+                fs::remove_dir_all(&path).ok();
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_abandoned(name: &str, dir: &Path) -> bool {
+    if LEGACY_STAGING_DIRS.contains(&name) {
+        return true;
+    }
+    match fs::OpenOptions::new().write(true).open(dir.join(OWNER_FILE)) {
+        // Holding the lock means its install is no longer running.
+        Ok(owner) => owner.try_lock().is_ok(),
+        Err(_) => fs::metadata(dir)
+            .and_then(|m| m.modified())
+            .is_ok_and(|t| t.elapsed().is_ok_and(|age| age > UNOWNED_GRACE)),
+    }
+}
+
+/// Windows file locking (antivirus, indexers) can make an instant rename fail,
+/// so try a few times before giving up.
+fn rename_with_retry(from: &Path, to: &Path) -> Result<()> {
+    let mut last_err = None;
+    for _ in 0..3 {
+        match fs::rename(from, to) {
+            Ok(()) => return Ok(()),
+            Err(e) => last_err = Some(e),
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+    Err(last_err.unwrap()).context("Failed to move extracted folder to installation path.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn staging_dirs(lang_dir: &Path) -> Vec<String> {
+        fs::read_dir(lang_dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|name| is_staging_dir(name))
+            .collect()
+    }
+
+    #[test]
+    fn recovery_keeps_running_installs_and_removes_dead_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let lang_dir = dir.path();
+        let running = Staging::create(lang_dir).unwrap();
+        assert!(running.path().join(OWNER_FILE).is_file());
+        assert!(!is_abandoned(&running.path().file_name().unwrap().to_string_lossy(), running.path()));
+
+        // An install that died: its owner file is no longer locked.
+        let dead = lang_dir.join(format!("{}1-1", STAGING_PREFIX));
+        fs::create_dir(&dead).unwrap();
+        fs::write(dead.join(OWNER_FILE), b"").unwrap();
+        // One being set up by an older mlvm, which has no owner file yet.
+        let unowned = lang_dir.join(format!("{}2-2", STAGING_PREFIX));
+        fs::create_dir(&unowned).unwrap();
+
+        recover(lang_dir).unwrap();
+        assert!(running.path().exists());
+        assert!(!dead.exists());
+        assert!(unowned.exists());
+
+        drop(running);
+        assert_eq!(staging_dirs(lang_dir), [format!("{}2-2", STAGING_PREFIX)]);
+    }
+
+    #[test]
+    fn staging_is_never_judged_while_it_is_set_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let lang_dir = dir.path().to_path_buf();
+        // Recovery racing with installs that start and finish.
+        let recovery = {
+            let lang_dir = lang_dir.clone();
+            std::thread::spawn(move || {
+                for _ in 0..200 {
+                    recover(&lang_dir).unwrap();
+                }
+            })
+        };
+        for _ in 0..200 {
+            let staging = Staging::create(&lang_dir).unwrap();
+            assert!(staging.path().join(OWNER_FILE).is_file(), "removed while in use");
+        }
+        recovery.join().unwrap();
+        assert!(staging_dirs(&lang_dir).is_empty());
+    }
+
+    #[test]
+    fn stamps_installs_made_before_the_marker() {
+        let dir = tempfile::tempdir().unwrap();
+        let lang_dir = dir.path();
+        fs::create_dir(lang_dir.join("v18.0.0")).unwrap();
+        assert!(!is_migrated(lang_dir));
+        recover(lang_dir).unwrap();
+        assert!(is_migrated(lang_dir));
+        assert!(is_complete(&lang_dir.join("v18.0.0")));
+
+        // Only once: later directories without the marker are interrupted installs.
+        fs::create_dir(lang_dir.join("v20.0.0")).unwrap();
+        recover(lang_dir).unwrap();
+        assert!(!is_complete(&lang_dir.join("v20.0.0")));
+    }
+}
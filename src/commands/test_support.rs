@@ -0,0 +1,86 @@
+// src/commands/test_support.rs
+//
+// Helpers shared by the unit tests: a tiny HTTP server that answers from a
+// closure, so downloads and index requests can be tested without the network.
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::thread;
+
+/// A request as the test server saw it.
+pub struct Request {
+    pub path: String,
+    /// Header names are lowercase.
+    pub headers: HashMap<String, String>,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+    /// Send only this many bytes of `body` before closing the connection.
+    pub cut_off: Option<usize>,
+}
+
+impl Response {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Response { status, headers: Vec::new(), body: body.into(), cut_off: None }
+    }
+
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Drops the connection after `len` bytes of the body, as a failing mirror would.
+    pub fn cut_off(mut self, len: usize) -> Self {
+        self.cut_off = Some(len);
+        self
+    }
+}
+
+/// Serves `handler` on a random local port until the test process exits and
+/// returns the base URL, e.g. `http://127.0.0.1:41234`.
+pub fn serve(handler: impl Fn(&Request) -> Response + Send + Sync + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handler = Arc::new(handler);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let handler = handler.clone();
+            thread::spawn(move || {
+                let mut reader = BufReader::new(&stream);
+                let mut line = String::new();
+                reader.read_line(&mut line).ok();
+                let path = line.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let mut headers = HashMap::new();
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+                    }
+                }
+
+                let response = handler(&Request { path, headers });
+                let mut head = format!(
+                    "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    response.status,
+                    response.body.len()
+                );
+                for (name, value) in &response.headers {
+                    head.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                head.push_str("\r\n");
+                let mut stream = &stream;
+                stream.write_all(head.as_bytes()).ok();
+                let len = response.cut_off.unwrap_or(response.body.len());
+                stream.write_all(&response.body[..len]).ok();
+            });
+        }
+    });
+    url
+}
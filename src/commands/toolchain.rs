@@ -0,0 +1,198 @@
+// src/commands/toolchain.rs
+use anyhow::Result;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// The archive formats published by the upstream projects we support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    TarGz,
+    TarZst,
+    Zip,
+}
+
+impl ArchiveKind {
+    /// Guesses the archive format from a file name like `go1.21.5.linux-amd64.tar.gz`.
+    pub fn from_filename(name: &str) -> Option<Self> {
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if name.ends_with(".tar.zst") {
+            Some(ArchiveKind::TarZst)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// A single entry from a language's remote version index.
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteVersion {
+    pub version: String,
+    /// The LTS codename (e.g. `Iron`) for upstreams that have LTS lines.
+    pub lts: Option<String>,
+}
+
+/// The file to download for one version on the current host.
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub url: String,
+    pub filename: String,
+    pub kind: ArchiveKind,
+    /// The folder inside the archive that holds the toolchain (e.g. `go` or `node-v20.11.0-linux-x64`).
+    pub root_dir: String,
+}
+
+/// Everything the engine needs to know about a language. The engine in
+/// `commands::engine` implements download, unpack, `current` switching and
+/// listing once on top of this, so every language gets the same commands.
+pub trait Toolchain {
+    /// Directory name under `~/.mlvm` (e.g. `node`).
+    const NAME: &'static str;
+    /// Human readable name used in messages (e.g. `Node.js`).
+    const DISPLAY_NAME: &'static str;
+    /// Upstream base URL of the version index; mirrors replace it (see `commands::mirror`).
+    const INDEX_URL: &'static str;
+    /// Upstream base URL of the archives and checksum files.
+    const DOWNLOAD_URL: &'static str;
+    /// How many entries `list-remote` prints. `None` prints the whole index.
+    const REMOTE_LIST_LIMIT: Option<usize> = None;
+    /// Fingerprints of the upstream's OpenPGP release keys that mlvm trusts out of the box.
+    /// `mlvm keys update` adds them to the keyring; see `commands::keys`.
+    const RELEASE_KEYS: &'static [&'static str] = &[];
+    /// Those keys themselves (ASCII-armored), built into mlvm from `keys/<lang>.asc`.
+    const RELEASE_KEYRING: &'static [u8] = &[];
+    /// Files that pin a version for a project directory and everything below it,
+    /// most specific first (e.g. `.node-version`).
+    const VERSION_FILES: &'static [&'static str] = &[];
+    /// The names of the variables `env_vars` sets, so they can be unset again.
+    const ENV_VARS: &'static [&'static str] = &[];
+
+    /// Turns user input into the name of the install directory,
+    /// e.g. `18.17.1` -> `v18.17.1` for Node.js or `go1.21.5` -> `1.21.5` for Go.
+    fn normalize_version(&self, version: &str) -> String;
+
+    /// Fetches the versions published upstream, newest first.
+    fn list_remote(&self) -> impl Future<Output = Result<Vec<RemoteVersion>>> + Send;
+
+    /// Picks the archive to download for `version` (already normalized) on this host.
+    fn artifact(&self, version: &str) -> impl Future<Output = Result<Artifact>> + Send;
+
+    /// The published SHA-256 of `artifact`, as lowercase hex.
+    fn sha256(&self, version: &str, artifact: &Artifact) -> impl Future<Output = Result<String>> + Send;
+
+    /// The version specs in a version file, highest priority first. By default the
+    /// first word that is not a `#` comment.
+    fn parse_version_file(&self, contents: &str) -> Vec<String> {
+        contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .find(|line| !line.is_empty())
+            .and_then(|line| line.split_whitespace().next())
+            .map(|spec| vec![spec.to_string()])
+            .unwrap_or_default()
+    }
+
+    /// Layout fix-ups applied to the unpacked toolchain before it is moved into place.
+    fn post_install(&self, _root: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// Directories, relative to an installed version, that must be on PATH.
+    fn bin_dirs(&self) -> Vec<PathBuf>;
+
+    /// Environment variables the tools expect, given the directory of the version in use.
+    fn env_vars(&self, _root: &Path) -> Vec<(&'static str, PathBuf)> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::bun::Bun;
+    use crate::commands::engine;
+    use crate::commands::go::Go;
+    use crate::commands::node::Node;
+    use crate::commands::python::Python;
+    use std::fs;
+
+    /// What the engine learns about a language, through the trait alone.
+    fn describe<T: Toolchain>(toolchain: &T, version: &str) -> (&'static str, String, Vec<&'static str>) {
+        let env = toolchain.env_vars(Path::new("/opt/v")).into_iter().map(|(name, _)| name).collect();
+        (T::NAME, toolchain.normalize_version(version), env)
+    }
+
+    #[test]
+    fn the_engine_sees_each_language_through_the_trait() {
+        assert_eq!(describe(&Node, "20.11.0"), ("node", "v20.11.0".into(), vec![]));
+        assert_eq!(describe(&Python, "3.12.1"), ("python", "3.12.1".into(), vec![]));
+        assert_eq!(describe(&Go, "go1.21.5"), ("go", "1.21.5".into(), vec!["GOROOT"]));
+        assert_eq!(describe(&Bun, "bun-v1.0.0"), ("bun", "v1.0.0".into(), vec![]));
+        // `ENV_VARS` names what `env_vars` sets, so a stale value can be unset.
+        assert_eq!(Go::ENV_VARS, ["GOROOT"]);
+        assert_eq!(Go.env_vars(Path::new("/opt/go")), [("GOROOT", PathBuf::from("/opt/go"))]);
+        assert_eq!(Bun.bin_dirs(), [PathBuf::new()]);
+    }
+
+    /// A language that relies on every default of the trait.
+    struct Minimal;
+
+    impl Toolchain for Minimal {
+        const NAME: &'static str = "minimal";
+        const DISPLAY_NAME: &'static str = "Minimal";
+        const INDEX_URL: &'static str = "http://127.0.0.1:9/index";
+        const DOWNLOAD_URL: &'static str = "http://127.0.0.1:9/dl";
+        const VERSION_FILES: &'static [&'static str] = &[".minimal-version"];
+
+        fn normalize_version(&self, version: &str) -> String {
+            version.to_string()
+        }
+
+        async fn list_remote(&self) -> Result<Vec<RemoteVersion>> {
+            Ok(vec![RemoteVersion { version: "1.0.0".into(), lts: None }])
+        }
+
+        async fn artifact(&self, version: &str) -> Result<Artifact> {
+            let filename = format!("minimal-{}.tar.gz", version);
+            Ok(Artifact {
+                url: format!("{}/{}", Self::DOWNLOAD_URL, filename),
+                kind: ArchiveKind::from_filename(&filename).unwrap(),
+                filename,
+                root_dir: "minimal".into(),
+            })
+        }
+
+        async fn sha256(&self, _version: &str, _artifact: &Artifact) -> Result<String> {
+            Ok(String::new())
+        }
+
+        fn bin_dirs(&self) -> Vec<PathBuf> {
+            vec![PathBuf::from("bin")]
+        }
+    }
+
+    #[tokio::test]
+    async fn a_new_language_only_fills_in_what_differs() {
+        let artifact = Minimal.artifact("1.0.0").await.unwrap();
+        assert_eq!(artifact.kind, ArchiveKind::TarGz);
+        assert!(Minimal.env_vars(Path::new("/opt/minimal")).is_empty());
+        assert!(Minimal.post_install(Path::new("/nonexistent")).is_ok());
+
+        // The engine finds its version files with the default parser.
+        let project = tempfile::tempdir().unwrap();
+        fs::write(project.path().join(".minimal-version"), "# pinned\n1.0.0 extra\n").unwrap();
+        let found = engine::project_version(&Minimal, project.path()).unwrap();
+        assert_eq!(found.specs, ["1.0.0"]);
+    }
+
+    #[test]
+    fn archive_kinds_come_from_the_file_name() {
+        assert_eq!(ArchiveKind::from_filename("go1.21.5.linux-amd64.tar.gz"), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::from_filename("node-v20.11.0-darwin-arm64.tgz"), Some(ArchiveKind::TarGz));
+        assert_eq!(ArchiveKind::from_filename("cpython-3.12.1.tar.zst"), Some(ArchiveKind::TarZst));
+        assert_eq!(ArchiveKind::from_filename("bun-linux-x64.zip"), Some(ArchiveKind::Zip));
+        assert_eq!(ArchiveKind::from_filename("SHASUMS256.txt"), None);
+    }
+}
@@ -0,0 +1,131 @@
+// src/commands/verify.rs
+//
+// SHA-256 checks for downloaded archives. Every upstream publishes hashes in
+// some form; the toolchains know where, this module knows how to compare.
+use anyhow::{bail, Context, Result};
+
+use super::error::{Error, ErrorKind};
+use super::mirror;
+use super::toolchain::Toolchain;
+
+/// Fails loudly when the `actual` hash of a download differs from the `expected` one.
+pub fn check_sha256(actual: &str, expected: &str, filename: &str) -> Result<()> {
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        bail!(Error::new(
+            ErrorKind::Checksum,
+            format!(
+                "Checksum mismatch for {}!\n  expected: {}\n  actual:   {}\n\
+                The download may be corrupted or tampered with. Refusing to install it.",
+                filename,
+                expected.trim(),
+                actual
+            )
+        ));
+    }
+    Ok(())
+}
+
+/// Finds the hash for `filename` in a `sha256sum`-style listing
+/// (`<hash>  <name>` or `<hash> *<name>` per line), as used by `SHASUMS256.txt` and `SHA256SUMS`.
+pub fn find_in_checksum_list(list: &str, filename: &str) -> Option<String> {
+    list.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next()?;
+        let name = parts.next()?.trim_start_matches('*');
+        (name == filename).then(|| hash.to_string())
+    })
+}
+
+/// Downloads a checksum listing that sits next to the artifact (same release directory)
+/// and returns the hash for `filename`.
+pub async fn fetch_from_sibling_list<T: Toolchain>(
+    artifact_url: &str,
+    list_name: &str,
+    filename: &str,
+) -> Result<String> {
+    let list_url = sibling_url(artifact_url, list_name);
+    let list = mirror::get::<T>(&list_url)
+        .await?
+        .error_for_status()
+        .with_context(|| format!("Failed to download checksums from {}", list_url))?
+        .text()
+        .await?;
+
+    find_in_checksum_list(&list, filename).with_context(|| no_entry(&list_url, filename))
+}
+
+/// For the toolchains: `source` publishes checksums, but none for `filename`.
+pub fn no_entry(source: &str, filename: &str) -> Error {
+    Error::new(ErrorKind::Checksum, format!("{} has no entry for {}", source, filename))
+}
+
+/// `https://host/dir/file.tar.gz` + `SHASUMS256.txt` -> `https://host/dir/SHASUMS256.txt`
+pub fn sibling_url(url: &str, name: &str) -> String {
+    match url.rsplit_once('/') {
+        Some((dir, _)) => format!("{}/{}", dir, name),
+        None => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::error;
+
+    const SHASUMS: &str = "\
+2f5d4d6d5e3f1b2a  node-v20.11.0-darwin-arm64.tar.gz
+9a8b7c6d5e4f3a2b  node-v20.11.0-linux-x64.tar.xz
+0123456789abcdef *node-v20.11.0-linux-x64.tar.gz
+
+";
+
+    #[test]
+    fn finds_entries_in_checksum_lists() {
+        assert_eq!(find_in_checksum_list(SHASUMS, "node-v20.11.0-linux-x64.tar.xz").as_deref(), Some("9a8b7c6d5e4f3a2b"));
+        // Binary-mode entries carry a `*` before the name.
+        assert_eq!(find_in_checksum_list(SHASUMS, "node-v20.11.0-linux-x64.tar.gz").as_deref(), Some("0123456789abcdef"));
+        assert_eq!(find_in_checksum_list(SHASUMS, "node-v20.11.0-linux-x64"), None);
+        assert_eq!(find_in_checksum_list(SHASUMS, "linux-x64.tar.gz"), None);
+        assert_eq!(find_in_checksum_list("", "a.tar.gz"), None);
+    }
+
+    #[test]
+    fn sibling_urls_share_the_release_directory() {
+        assert_eq!(
+            sibling_url("https://nodejs.org/dist/v20.11.0/node-v20.11.0-linux-x64.tar.xz", "SHASUMS256.txt"),
+            "https://nodejs.org/dist/v20.11.0/SHASUMS256.txt"
+        );
+        assert_eq!(
+            sibling_url("https://github.com/oven-sh/bun/releases/download/bun-v1.1.0/bun-linux-x64.zip", "SHASUMS256.txt.asc"),
+            "https://github.com/oven-sh/bun/releases/download/bun-v1.1.0/SHASUMS256.txt.asc"
+        );
+        assert_eq!(sibling_url("archive.tar.gz", "SHA256SUMS"), "SHA256SUMS");
+    }
+
+    #[tokio::test]
+    async fn only_missing_entries_are_checksum_errors() {
+        use crate::commands::python::Python;
+        use crate::commands::test_support::{self, Response};
+
+        let url = test_support::serve(|request| match request.path.as_str() {
+            "/ok/SHA256SUMS" => Response::new(200, SHASUMS),
+            _ => Response::new(404, "Not Found"),
+        });
+        let (ok, gone) = (format!("{}/ok/a.tar.gz", url), format!("{}/gone/a.tar.gz", url));
+        let hash = fetch_from_sibling_list::<Python>(&ok, "SHA256SUMS", "node-v20.11.0-linux-x64.tar.xz").await;
+        assert_eq!(hash.unwrap(), "9a8b7c6d5e4f3a2b");
+
+        let missing = fetch_from_sibling_list::<Python>(&ok, "SHA256SUMS", "a.tar.gz").await.unwrap_err();
+        assert_eq!(error::kind(&missing), ErrorKind::Checksum);
+
+        let unreachable = fetch_from_sibling_list::<Python>(&gone, "SHA256SUMS", "a.tar.gz").await.unwrap_err();
+        assert_eq!(error::kind(&unreachable), ErrorKind::Network);
+    }
+
+    #[test]
+    fn hash_comparison_ignores_case_and_whitespace() {
+        assert!(check_sha256("abcdef", "ABCDEF\n", "a.tar.gz").is_ok());
+        let err = check_sha256("abcdef", "abcdee", "a.tar.gz").unwrap_err();
+        assert_eq!(error::kind(&err), ErrorKind::Checksum);
+    }
+}
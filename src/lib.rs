@@ -0,0 +1,41 @@
+//! mlvm as a library: the version index, resolver, installer and activation
+//! behind the `mlvm` command, as functions that return structured reports
+//! (the same ones `--json` prints) instead of printing.
+//!
+//! ```no_run
+//! # async fn provision() -> anyhow::Result<()> {
+//! use mlvm::Node;
+//!
+//! let resolved = mlvm::resolve(&Node, "lts/*").await?;
+//! let report = mlvm::install(&Node, &resolved.version, false).await?;
+//! let active = mlvm::use_version(&Node, &report.version)?;
+//! println!("PATH entries: {:?}", active.bin_dirs);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Nothing is printed to stdout or stderr. Settings that the CLI takes as global
+//! flags are process-wide and may be set once before the first call:
+//! [`set_home`] (`--home`, else `MLVM_HOME` is used), [`set_offline`] (`--offline`) and [`set_lock_timeout`]
+//! (`--lock-timeout`). Errors are `anyhow::Error`s; [`error_kind`] tells them apart.
+//!
+//! Only the items re-exported here are the library's API. `cli` and `commands` are
+//! public for the `mlvm` binary and the integration tests, and may change at any time.
+#[doc(hidden)]
+pub mod cli;
+#[doc(hidden)]
+pub mod commands;
+
+pub use commands::bun::Bun;
+pub use commands::engine::{
+    current, install, list_aliases, list_local, list_remote, remove_alias, resolve, set_alias,
+    set_home, show_alias, uninstall, use_version, ActiveVersion, Alias, AliasList, InstallReport,
+    LocalList, LocalVersion, RemoteList, Resolved, UninstallReport,
+};
+pub use commands::error::{kind as error_kind, Error, ErrorKind};
+pub use commands::go::Go;
+pub use commands::http::set_offline;
+pub use commands::lock::set_timeout as set_lock_timeout;
+pub use commands::node::Node;
+pub use commands::python::Python;
+pub use commands::toolchain::{RemoteVersion, Toolchain};
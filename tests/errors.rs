@@ -0,0 +1,71 @@
+// What scripts see when a command fails: the exit code and, with `--json`, the
+// error document on stdout. Each run is a process of its own.
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn mlvm(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mlvm"))
+        .env_remove("MLVM_HOME")
+        .env_remove("MLVM_OFFLINE")
+        .arg("--home")
+        .arg(home)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Runs a command that must fail and returns its exit code and JSON error.
+fn failure(home: &Path, args: &[&str]) -> (i32, serde_json::Value) {
+    let output = mlvm(home, &[&["--json"], args].concat());
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .unwrap_or_else(|e| panic!("{e}: {}", String::from_utf8_lossy(&output.stdout)));
+    let error = &document["error"];
+    assert_eq!(error["exit_code"], output.status.code().unwrap(), "{document}");
+    assert!(error["message"].is_string() && error["causes"].is_array(), "{document}");
+    (output.status.code().unwrap(), error.clone())
+}
+
+#[test]
+fn failures_have_a_kind_and_a_fixed_exit_code() {
+    let home = tempfile::tempdir().unwrap();
+    let home = home.path();
+
+    let (code, error) = failure(home, &["node", "use", "20"]);
+    assert_eq!((code, error["kind"].as_str()), (4, Some("not_installed")));
+    assert_eq!(error["message"], "No installed Node.js version matches '20'.");
+    assert_eq!(error["hint"], "Run `mlvm node install 20` first.");
+
+    let (code, error) = failure(home, &["node", "alias", "work"]);
+    assert_eq!((code, error["kind"].as_str()), (3, Some("not_found")));
+
+    let (code, error) = failure(home, &["node", "alias", "20", "18"]);
+    assert_eq!((code, error["kind"].as_str()), (2, Some("invalid_input")));
+
+    let (code, error) = failure(home, &["--offline", "node", "list-remote"]);
+    assert_eq!((code, error["kind"].as_str()), (8, Some("offline")));
+    assert!(error["hint"].as_str().unwrap().contains("--offline"));
+}
+
+#[test]
+fn waiting_too_long_for_a_lock_is_its_own_failure() {
+    let home = tempfile::tempdir().unwrap();
+    let locks = home.path().join("node/.locks");
+    fs::create_dir_all(&locks).unwrap();
+    let held = fs::File::create(locks.join("language.lock")).unwrap();
+    held.lock().unwrap();
+
+    let (code, error) = failure(home.path(), &["--lock-timeout", "0s", "node", "alias", "work", "20"]);
+    assert_eq!((code, error["kind"].as_str()), (9, Some("locked")));
+}
+
+#[test]
+fn without_json_the_error_and_hint_go_to_stderr() {
+    let home = tempfile::tempdir().unwrap();
+    let output = mlvm(home.path(), &["node", "use", "20"]);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("Error: No installed Node.js version matches '20'."), "{stderr}");
+    assert!(stderr.contains("\nHint: Run `mlvm node install 20` first."), "{stderr}");
+}
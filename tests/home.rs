@@ -0,0 +1,24 @@
+// The mlvm home is process-wide, so these run mlvm in processes of their own.
+use std::path::Path;
+use std::process::Command;
+
+fn mlvm(cwd: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_mlvm"))
+        .current_dir(cwd)
+        .env_remove("MLVM_HOME")
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn a_relative_home_is_made_absolute() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path().canonicalize().unwrap();
+    let shims = dir.join("relhome").join("shims");
+    let env = mlvm(&dir, &["--home", "relhome", "--json", "env", "--shell", "bash"]);
+    let activation: serde_json::Value = serde_json::from_str(&env).unwrap();
+    assert_eq!(activation["path"][0].as_str(), shims.to_str());
+}
@@ -0,0 +1,50 @@
+// The library API against a fresh mlvm home, with the network switched off.
+// The home is process-wide, so this runs in a process of its own.
+#![cfg(unix)]
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+#[tokio::test]
+async fn the_library_manages_installed_versions() {
+    let home = tempfile::tempdir().unwrap();
+    mlvm::set_offline(true);
+    mlvm::set_home(Some(home.path().to_path_buf())).unwrap();
+    // The same home again is fine; another one is too late.
+    mlvm::set_home(Some(home.path().to_path_buf())).unwrap();
+    let err = mlvm::set_home(Some(home.path().join("other"))).unwrap_err();
+    assert_eq!(mlvm::error_kind(&err), mlvm::ErrorKind::InvalidInput);
+
+    assert!(mlvm::list_local(&mlvm::Node).unwrap().versions.is_empty());
+    let err = mlvm::use_version(&mlvm::Node, "20").unwrap_err();
+    assert_eq!(mlvm::error_kind(&err), mlvm::ErrorKind::NotInstalled);
+    // Nothing is cached yet, so offline there is nothing to resolve against.
+    let err = mlvm::resolve(&mlvm::Node, "lts/*").await.unwrap_err();
+    assert_eq!(mlvm::error_kind(&err), mlvm::ErrorKind::Offline);
+
+    let bin = home.path().join("node/v20.11.0/bin");
+    fs::create_dir_all(&bin).unwrap();
+    fs::write(bin.join("node"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(bin.join("node"), fs::Permissions::from_mode(0o755)).unwrap();
+    // What a finished install leaves behind.
+    fs::write(home.path().join("node/v20.11.0/.mlvm-installed"), "").unwrap();
+
+    mlvm::set_alias(&mlvm::Node, "work", "20").unwrap();
+    assert_eq!(mlvm::show_alias(&mlvm::Node, "work").unwrap().target, "20");
+    let active = mlvm::use_version(&mlvm::Node, "work").unwrap();
+    assert_eq!(active.version.as_deref(), Some("v20.11.0"));
+    assert_eq!(active.bin_dirs, [home.path().join("node/current/bin")]);
+    assert_eq!(mlvm::current(&mlvm::Node).unwrap().version.as_deref(), Some("v20.11.0"));
+
+    let local = mlvm::list_local(&mlvm::Node).unwrap();
+    assert_eq!(local.current.as_deref(), Some("v20.11.0"));
+    assert_eq!(local.versions.len(), 1);
+    assert!(local.versions[0].current);
+
+    let err = mlvm::uninstall(&mlvm::Node, "v20.11.0", false).unwrap_err();
+    assert_eq!(mlvm::error_kind(&err), mlvm::ErrorKind::InvalidInput);
+    let report = mlvm::uninstall(&mlvm::Node, "v20.11.0", true).unwrap();
+    assert!(report.was_current);
+    assert_eq!(mlvm::current(&mlvm::Node).unwrap().version, None);
+    mlvm::remove_alias(&mlvm::Node, "work").unwrap();
+    assert!(mlvm::list_aliases(&mlvm::Node).unwrap().aliases.is_empty());
+}
@@ -0,0 +1,72 @@
+// `list` as a user sees it, in a process of its own since the mlvm home is process-wide.
+#![cfg(unix)]
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn mlvm(home: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_mlvm"))
+        .env_remove("MLVM_HOME")
+        .arg("--home")
+        .arg(home)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn fake_install(home: &Path, version: &str) {
+    let bin = home.join("node").join(version).join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    fs::write(bin.join("node"), "#!/bin/sh\n").unwrap();
+}
+
+#[test]
+fn list_marks_the_current_version_and_hides_scratch_directories() {
+    let home = tempfile::tempdir().unwrap();
+    let home = home.path();
+    fake_install(home, "v20.11.0");
+    fake_install(home, "v18.19.0");
+    fs::create_dir_all(home.join("node/.meta")).unwrap();
+    fs::write(
+        home.join("node/.meta/v20.11.0.json"),
+        r#"{"version": "v20.11.0", "source_url": "https://nodejs.org/dist/v20.11.0/node.tar.gz", "installed_at": 1705000000}"#,
+    )
+    .unwrap();
+    mlvm(home, &["node", "use", "20"]);
+    // Left behind by an older mlvm, and by an install that never finished.
+    fs::create_dir_all(home.join("node/temp_unpack/node-v21.0.0")).unwrap();
+    fake_install(home, "v21.0.0");
+
+    let text = mlvm(home, &["node", "list"]);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "Ignoring incomplete install v21.0.0. Run `mlvm node install v21.0.0` to repair it.");
+    let lines = &lines[1..];
+    assert_eq!(lines[0], "Installed Node.js versions:");
+    assert!(lines[1].starts_with("  v18.19.0 "), "{text}");
+    // Without a metadata record the source is unknown.
+    assert!(lines[1].ends_with("  -"), "{text}");
+    assert!(lines[2].starts_with("* v20.11.0 "), "{text}");
+    assert!(
+        lines[2].ends_with("2024-01-11  https://nodejs.org/dist/v20.11.0/node.tar.gz  (current)"),
+        "{text}"
+    );
+    assert_eq!(lines.len(), 3, "{text}");
+    assert!(!text.contains("temp_unpack"), "{text}");
+
+    let list: serde_json::Value = serde_json::from_str(&mlvm(home, &["--json", "node", "list"])).unwrap();
+    assert_eq!(list["language"], "node");
+    assert_eq!(list["current"], "v20.11.0");
+    assert_eq!(list["incomplete"], serde_json::json!(["v21.0.0"]));
+    let versions = list["versions"].as_array().unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[0]["version"], "v18.19.0");
+    assert_eq!(versions[0]["current"], false);
+    assert!(versions[0]["source_url"].is_null());
+    assert_eq!(versions[1]["version"], "v20.11.0");
+    assert_eq!(versions[1]["current"], true);
+    assert_eq!(versions[1]["size"], 10);
+    assert!(versions[1]["installed_at"].as_str().unwrap().starts_with("2024-01-11T"));
+    assert_eq!(versions[1]["source_url"], "https://nodejs.org/dist/v20.11.0/node.tar.gz");
+}
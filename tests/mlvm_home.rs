@@ -0,0 +1,11 @@
+// `MLVM_HOME` is read from the environment, so this sets it in a test process
+// of its own, with no other test running beside it.
+
+#[test]
+fn library_callers_honor_mlvm_home() {
+    let dir = tempfile::tempdir().unwrap();
+    let home = dir.path().join("from-env");
+    // SAFETY: this is the only test in this process, and nothing else runs in it yet.
+    unsafe { std::env::set_var("MLVM_HOME", &home) };
+    assert_eq!(mlvm::commands::engine::mlvm_dir().unwrap(), home);
+}
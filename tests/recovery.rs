@@ -0,0 +1,20 @@
+// Installs made before the install marker existed, seen through a fresh mlvm home.
+// The home is process-wide, so this runs in a process of its own.
+#![cfg(unix)]
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+#[test]
+fn installs_without_the_marker_get_shims() {
+    let home = tempfile::tempdir().unwrap();
+    let bin = home.path().join("node/v20.11.0/bin");
+    fs::create_dir_all(&bin).unwrap();
+    fs::write(bin.join("node"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(bin.join("node"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    mlvm::set_home(Some(home.path().to_path_buf())).unwrap();
+    assert_eq!(mlvm::commands::shims::regenerate().unwrap(), ["node"]);
+    assert_eq!(mlvm::commands::engine::installed_versions::<mlvm::Node>().unwrap(), ["v20.11.0"]);
+    // Reading does not stamp them; that is left to the commands that change the directory.
+    assert!(!home.path().join("node/v20.11.0/.mlvm-installed").exists());
+}
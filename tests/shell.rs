@@ -0,0 +1,43 @@
+// `env` and `hook-env` read the environment of the shell, so these run mlvm in
+// processes of their own.
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn mlvm(home: &Path, vars: &[(&str, &str)], args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_mlvm"))
+        .current_dir(home)
+        .env_remove("MLVM_HOME")
+        .env_remove("GOROOT")
+        .env_remove("MLVM_HOOK_VARS")
+        .envs(vars.iter().copied())
+        .arg("--home")
+        .arg(home)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    output
+}
+
+#[test]
+fn a_bad_session_version_only_costs_its_language() {
+    let home = tempfile::tempdir().unwrap();
+    let output = mlvm(home.path(), &[("MLVM_NODE_VERSION", "99")], &["env", "--shell", "bash"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("export PATH="));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("ignoring MLVM_NODE_VERSION"));
+
+    let output = mlvm(home.path(), &[("MLVM_NODE_VERSION", "99")], &["hook-env", "--shell", "bash"]);
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("ignoring MLVM_NODE_VERSION"));
+}
+
+#[test]
+fn env_unsets_variables_left_by_a_go_that_is_no_longer_selected() {
+    let home = tempfile::tempdir().unwrap();
+    let ours = home.path().join("go/1.22.0/go");
+    let output = mlvm(home.path(), &[("GOROOT", ours.to_str().unwrap())], &["env", "--shell", "bash"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("unset GOROOT\n"));
+
+    let output = mlvm(home.path(), &[("GOROOT", "/usr/local/go")], &["env", "--shell", "bash"]);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("GOROOT"));
+}